@@ -1,17 +1,15 @@
-import init, { gradient_draw, g_update_sides } from "../pkg/webassembly_webgl_viewer.js";
+import init, { gradient_init, g_update_sides } from "../pkg/webassembly_webgl_viewer.js";
 
 const CANVAS_ID = "gradient";
 
+let animation = null;
+
 async function run() {
   
   await init();
   document.getElementById("shape_sides").value = 10;
 
-  function loop() {
-    gradient_draw(CANVAS_ID);
-    requestAnimationFrame(loop);
-  }
-  requestAnimationFrame(loop);
+  animation = gradient_init(CANVAS_ID);
 
 }
 
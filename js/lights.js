@@ -1,302 +1,94 @@
-// Set up the scene
-const scene = new THREE.Scene();
-const camera = createCamera();
-const renderer = new THREE.WebGLRenderer({ antialias: true });
-renderer.shadowMap.enabled = true; // Enable shadow maps in the renderer
-const container = document.getElementById('canvas-container');
-container.appendChild(renderer.domElement);
-const controls = createControls();
-const lights = createLights();
-var selectedLightIndex = 0;
-const colorPicker = createColorPicker();
-
-// Initialize the scene
-function createCamera() {
-    const cam = new THREE.PerspectiveCamera(75, window.innerWidth / window.innerHeight, 0.1, 1000);
-    cam.position.set(75, 30, 0);
-    cam.lookAt(0, 0, 0);
-    return cam;
-}
-
-function createControls() {
-    const ctrl = new THREE.OrbitControls(camera, renderer.domElement);
-    ctrl.enableDamping = true;
-    ctrl.dampingFactor = 0.25;
-    ctrl.screenSpacePanning = false;
-    ctrl.maxPolarAngle = Math.PI / 2;
-    return ctrl;
-}
-
-
-function createColorPicker() {
-    var colorPicker = new iro.ColorPicker("#picker", {
-        width: 150,
-        color: "#ffffff"
-    });
-    
-    colorPicker.on(["color:init", "color:change"], function(color){
-        lights[selectedLightIndex].spotlight.color.set(new THREE.Color(color.hexString));
-        lights[selectedLightIndex].box.material.color.set(new THREE.Color(colorPicker.color.hexString));
-    });
-
-}
-
-function createLights() {
-    
-    const lightsArr = [];
-    const numberOfLights = 3;
-
-    for (let i = 0; i < numberOfLights; i++) {
-
-        const spotlight = new THREE.SpotLight(0xffffff);
-        spotlight.position.set(-50, 50, -50 + i * (100 / (numberOfLights-1)));
-        spotlight.target.position.set(0, 0, -50 + i * (100 / (numberOfLights-1)));
-        spotlight.distance = 0; // infinite throw
-        spotlight.angle = 0.1; // how wide the beam is
-        spotlight.penumbra = 0.6; // how blurry the beam is
-        spotlight.castShadow = true;
-
-        // Create a box to represent the spotlight
-        const boxGeometry = new THREE.BoxGeometry(5, 5, 15);
-        const boxMaterial = new THREE.MeshBasicMaterial({ color: 0xffffff });
-        const spotlightBox = new THREE.Mesh(boxGeometry, boxMaterial);
-        spotlightBox.position.copy(spotlight.position);
-        spotlightBox.visible = true;
-        const direction = new THREE.Vector3().subVectors(spotlight.target.position, spotlight.position).normalize();
-        spotlightBox.lookAt(direction.add(spotlight.position));
-
-        // spot beams
-        const spotlightHelper = new THREE.SpotLightHelper(spotlight);
-
-        scene.add(spotlightBox);
-        scene.add(spotlight);
-        scene.add(spotlight.target);
-        scene.add(spotlightHelper);
-        
-        lightsArr.push({ spotlight, helper: spotlightHelper, box: spotlightBox });
-        
-    }
-
-    return lightsArr;
-}
-
-// Add initial scene objects
-function initScene() {
-    addFloor();
-    addGround();
-    addAmbientLight();
-    const cube = addCube();
-    setupEventListeners();
-
-    decodeLightsFromURL();
-    // make lights look at their target
-    lights.forEach(light => {
-        const direction = new THREE.Vector3().subVectors(light.spotlight.target.position, light.spotlight.position).normalize();
-        light.box.lookAt(direction.add(light.spotlight.position));
-        light.box.material.color.set(light.spotlight.color);
-    });
-
-    // Add DragControls
-    const dragControls = new THREE.DragControls([cube], camera, renderer.domElement);
-    dragControls.addEventListener('dragstart', function(event) {
-        controls.enabled = false;
-    });
-    dragControls.addEventListener('dragend', function(event) {
-        controls.enabled = true;
-    });
-
-}
-
-function addCube() {
-    const geometry = new THREE.BoxGeometry();
-    const material = new THREE.MeshStandardMaterial({ color: 0xf00ff0 });
-    const cube = new THREE.Mesh(geometry, material);
-    cube.scale.set(5, 15, 7);
-    cube.position.set(25, 5, 0);
-    cube.castShadow = true;
-    scene.add(cube);
-    return cube;
-}
-
-function addFloor() {
-    const floorGeo = new THREE.PlaneGeometry(10000, 10000);
-    const floorMat = new THREE.MeshStandardMaterial({ color: 0x80F080, side: THREE.DoubleSide });
-    const floor = new THREE.Mesh(floorGeo, floorMat);
-    floor.rotation.x = -Math.PI / 2;
-    floor.position.y = -0.1;
-    floor.receiveShadow = true;
-    scene.add(floor);
-}
-
-function addGround() {
-    const groundGeometry = new THREE.PlaneGeometry(100, 100);
-    const groundMaterial = new THREE.MeshStandardMaterial({ color: 0x808080, side: THREE.DoubleSide });
-    const ground = new THREE.Mesh(groundGeometry, groundMaterial);
-    ground.rotation.x = -Math.PI / 2;
-    ground.receiveShadow = true;
-    scene.add(ground);
-}
-
-function addAmbientLight() {
-    const ambientLight = new THREE.AmbientLight(0xffffff, 0.5);
-    scene.add(ambientLight);
-}
-
-// Event listeners
-function setupEventListeners() {
-    
-
-    document.getElementById('light-selector').addEventListener('change', function(event) {
-        selectedLightIndex = parseInt(event.target.value);
-        updateControls();
-    });
-
-    document.getElementById('left_right').addEventListener('input', function(event) {
-        const angle = parseFloat(event.target.value);
-        angle_to_xy(angle);
-        const light = lights[selectedLightIndex];
-        const direction = new THREE.Vector3().subVectors(light.spotlight.target.position, light.spotlight.position).normalize();
-        light.box.lookAt(direction.add(light.spotlight.position));
-        encodeLightsToURL();
-    });
-
-    document.getElementById('up_down').addEventListener('input', function(event) {
-        const y = parseFloat(event.target.value);
-        lights[selectedLightIndex].spotlight.target.position.y = y;
-        const light = lights[selectedLightIndex];
-        const direction = new THREE.Vector3().subVectors(light.spotlight.target.position, light.spotlight.position).normalize();
-        light.box.lookAt(direction.add(light.spotlight.position));
-        encodeLightsToURL();
-    });
-
-    document.getElementById('intensity').addEventListener('input', function(event) {
-        const intensity = parseFloat(event.target.value) / 10;
-        lights[selectedLightIndex].spotlight.intensity = intensity;
-        encodeLightsToURL();
-    });
-
-    document.getElementById('size').addEventListener('input', function(event) {
-        const size = parseFloat(event.target.value) / 100;
-        lights[selectedLightIndex].spotlight.angle = size;
-        encodeLightsToURL();
-    });
-
-    document.getElementById('focus').addEventListener('input', function(event) {
-        const focus = parseFloat(event.target.value) / 100;
-        lights[selectedLightIndex].spotlight.penumbra = focus;
-        encodeLightsToURL();
-    });
-
-    window.addEventListener('resize', () => setSize());
+import init, {
+  lights_init,
+  lights_set_turn,
+  lights_set_target_height,
+  lights_set_intensity,
+  lights_set_size,
+  lights_set_focus,
+  lights_set_colour,
+  lights_set_guides,
+  lights_set_shadow_resolution,
+  lights_set_shadow_bias,
+  lights_shadow_resolution,
+  lights_shadow_bias,
+  lights_turn,
+  lights_target_height,
+  lights_intensity,
+  lights_size,
+  lights_focus,
+  lights_colour,
+} from "../pkg/webassembly_webgl_viewer.js";
+
+const CANVAS_ID = "lights";
+
+let animation = null;
+
+let selectedLightIndex = 0;
+let colorPicker = null;
+
+function slider(id, set, scale) {
+  const input = document.getElementById(id);
+  input.addEventListener("input", () => {
+    set(selectedLightIndex, parseFloat(input.value) / scale);
+  });
+}
+
+function showValue(id, value) {
+  document.getElementById(id).value = value;
+  document.getElementById(id + "_value").innerText = Math.round(value);
 }
 
 // update the controls
 function updateControls() {
-    const selectedLight = lights[selectedLightIndex].spotlight;
-
-    document.getElementById('intensity').value = selectedLight.intensity * 10;
-    document.getElementById('intensity_value').innerText = Math.round(selectedLight.intensity * 10);
-
-    document.getElementById('size').value = selectedLight.angle * 100;
-    document.getElementById('size_value').innerText = Math.round(selectedLight.angle * 100);
-
-    document.getElementById('focus').value = selectedLight.penumbra * 100;
-    document.getElementById('focus_value').innerText = Math.round(selectedLight.penumbra * 100);
-
-    document.getElementById('up_down').value = selectedLight.target.position.y;
-    document.getElementById('up_down_value').innerText = Math.round(selectedLight.target.position.y);
-
-    const lightPosition = selectedLight.position;
-    const angle = Math.atan2(
-        lightPosition.x - selectedLight.target.position.x,
-        lightPosition.z - selectedLight.target.position.z 
-    ) * (180 / Math.PI) + 180;
-    document.getElementById('left_right').value = angle;
-    document.getElementById('left_right_value').innerText = Math.round(angle);
+  const i = selectedLightIndex;
+  showValue("intensity", lights_intensity(i) * 10);
+  showValue("size", lights_size(i) * 100);
+  showValue("focus", lights_focus(i) * 100);
+  showValue("up_down", lights_target_height(i));
+  showValue("left_right", lights_turn(i));
+  colorPicker.color.hexString = "#" + lights_colour(i);
 }
 
+async function run() {
 
-function angle_to_xy(value) {
-    const angle = parseFloat(value);
-    const radians = angle * (Math.PI / 180);
-    const radius = 50;
-    const light = lights[selectedLightIndex].spotlight;
-    light.target.position.x = radius * Math.sin(radians) + light.position.x;
-    light.target.position.z = radius * Math.cos(radians) + light.position.z;
-}
+  await init();
 
-function animate() {
-    requestAnimationFrame(animate);
-    renderer.render(scene, camera);
-    lights.forEach(light => light.helper.update());
-    if (document.getElementById('help_lines').checked) {
-        lights.forEach(light => scene.add(light.helper));
-    } else {
-        scene.children = scene.children.filter(child => !(child instanceof THREE.SpotLightHelper));
-    }
+  // the canvas keeps its own drawing buffer in step with its size on the page
+  animation = lights_init(CANVAS_ID);
 
-}
+  colorPicker = new iro.ColorPicker("#picker", {
+    width: 150,
+    color: "#" + lights_colour(selectedLightIndex),
+  });
+  colorPicker.on("color:change", (color) => {
+    lights_set_colour(selectedLightIndex, color.hexString);
+  });
 
-function setSize() {
-    const width = container.clientWidth;
-    const height = container.clientHeight;
-    renderer.setSize(width, height);
-    camera.aspect = width / height;
-    camera.updateProjectionMatrix();
-}
+  document.getElementById("light-selector").addEventListener("change", (e) => {
+    selectedLightIndex = parseInt(e.target.value);
+    updateControls();
+  });
 
-// Initialize everything
-initScene();
-animate();
-setSize();
-updateControls();
+  slider("left_right", lights_set_turn, 1);
+  slider("up_down", lights_set_target_height, 1);
+  slider("intensity", lights_set_intensity, 10);
+  slider("size", lights_set_size, 100);
+  slider("focus", lights_set_focus, 100);
 
+  const helpLines = document.getElementById("help_lines");
+  helpLines.addEventListener("change", () => lights_set_guides(helpLines.checked));
+  lights_set_guides(helpLines.checked);
 
+  // the bias slider is in steps of 0.0001
+  const shadowBias = document.getElementById("shadow_bias");
+  shadowBias.addEventListener("input", () => lights_set_shadow_bias(parseFloat(shadowBias.value) / 10000));
+  showValue("shadow_bias", lights_shadow_bias() * 10000);
 
+  const shadowResolution = document.getElementById("shadow_resolution");
+  shadowResolution.addEventListener("change", () => lights_set_shadow_resolution(parseInt(shadowResolution.value)));
+  shadowResolution.value = lights_shadow_resolution();
 
-function encodeLightsToURL() {
-    const params = new URLSearchParams();
-    lights.forEach((light, i) => {
-        const { spotlight: sp } = light;
-        params.set(`l${i}_c`, sp.color.getHexString());
-        params.set(`l${i}_i`, sp.intensity.toFixed(2));
-        params.set(`l${i}_a`, sp.angle.toFixed(2));
-        params.set(`l${i}_p`, sp.penumbra.toFixed(2));
-        params.set(`l${i}_ty`, sp.target.position.y.toFixed(2));
-        
-        const dir = new THREE.Vector3().subVectors(sp.target.position, sp.position).normalize();
-        params.set(`l${i}_dx`, dir.x.toFixed(2));
-        params.set(`l${i}_dy`, dir.y.toFixed(2));
-        params.set(`l${i}_dz`, dir.z.toFixed(2));
-    });
-    window.history.replaceState({}, '', `${window.location.pathname}?${params.toString()}`);
-}
-function decodeLightsFromURL() {
-    const params = new URLSearchParams(window.location.search);
-    lights.forEach((light, i) => {
-        const { spotlight: sp } = light;
-        
-        const color = params.get(`l${i}_c`);
-        if (color) sp.color.set(`#${color}`);
-        
-        const intensity = parseFloat(params.get(`l${i}_i`));
-        if (!isNaN(intensity)) sp.intensity = intensity;
-        
-        const angle = parseFloat(params.get(`l${i}_a`));
-        if (!isNaN(angle)) sp.angle = angle;
-        
-        const penumbra = parseFloat(params.get(`l${i}_p`));
-        if (!isNaN(penumbra)) sp.penumbra = penumbra;
-        
-        const targetY = parseFloat(params.get(`l${i}_ty`));
-        if (!isNaN(targetY)) sp.target.position.y = targetY;
-        
-        const dirX = parseFloat(params.get(`l${i}_dx`));
-        const dirY = parseFloat(params.get(`l${i}_dy`));
-        const dirZ = parseFloat(params.get(`l${i}_dz`));
-        if (!isNaN(dirX) && !isNaN(dirY) && !isNaN(dirZ)) {
-            const dir = new THREE.Vector3(dirX, dirY, dirZ).normalize();
-            sp.target.position.copy(sp.position).add(dir.multiplyScalar(50));
-        }
-    });
+  updateControls();
 }
+
+run();
@@ -1,18 +1,16 @@
-import init, { point_draw, p_update_resolution, p_update_box } from "../pkg/webassembly_webgl_viewer.js";
+import init, { point_init, p_update_resolution, p_update_box } from "../pkg/webassembly_webgl_viewer.js";
 
 const CANVAS_ID = "point";
 
+let animation = null;
+
 async function run() {
   
   await init();
   
   document.getElementById("shape_sides").value = 10;
 
-  function loop() {
-    point_draw(CANVAS_ID);
-    requestAnimationFrame(loop);
-  }
-  requestAnimationFrame(loop);
+  animation = point_init(CANVAS_ID);
 
 }
 
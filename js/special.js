@@ -1,18 +1,16 @@
-import init, { draw, update_sides, update_rotation_speed, update_colour_speed } from "../pkg/webassembly_webgl_viewer.js";
+import init, { special_init, update_sides, update_rotation_speed, update_colour_speed } from "../pkg/webassembly_webgl_viewer.js";
 
 const CANVAS_ID = "special";
 
+let animation = null;
+
 async function run() {
   
   await init();
   document.getElementById("shape_sides").value = 10;
   document.getElementById("rotation_speed").value = 50;
 
-  function loop() {
-    draw(CANVAS_ID);
-    requestAnimationFrame(loop);
-  }
-  requestAnimationFrame(loop);
+  animation = special_init(CANVAS_ID);
 
 }
 
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use crate::error::ViewerError;
+use crate::listeners::Listeners;
+use crate::utils::{evict_gl_cache, get_canvas};
+use crate::viewport::ResizeWatch;
+
+type Frame = Box<dyn FnMut() -> Result<(), ViewerError>>;
+
+struct Inner {
+    frame: Frame,
+    listeners: Listeners,
+    // keeping the canvas fitted to the page, when the demo has one
+    resize: Option<ResizeWatch>,
+    // the canvas it draws on, whose gl cache goes with the loop
+    canvas: Option<HtmlCanvasElement>,
+    running: bool,
+    // the animation frame asked for, so stopping can take it back
+    requested: Option<i32>,
+    // the one closure every frame is asked for with, made on the first request
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    callback: Option<Closure<dyn FnMut()>>,
+}
+
+// draws a demo every animation frame, with its event listeners on the page, until
+// it is stopped. handed to js so a page can stop and start demos as they come and
+// go, and freeing it stops the demo for good
+#[wasm_bindgen]
+pub struct AnimationLoop {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl AnimationLoop {
+    // a stopped loop, `start` puts the listeners on the page and draws the first frame
+    pub fn new(listeners: Listeners, frame: impl FnMut() -> Result<(), ViewerError> + 'static) -> AnimationLoop {
+        AnimationLoop {
+            inner: Rc::new(RefCell::new(Inner {
+                frame: Box::new(frame),
+                listeners,
+                resize: None,
+                canvas: None,
+                running: false,
+                requested: None,
+                callback: None,
+            })),
+        }
+    }
+
+    // a running loop that draws on a canvas and keeps it fitted to the page, the
+    // way every demo starts
+    pub fn for_canvas(
+        canvas_id: &str,
+        listeners: Listeners,
+        frame: impl FnMut() -> Result<(), ViewerError> + 'static,
+    ) -> Result<AnimationLoop, ViewerError> {
+        let canvas = get_canvas(canvas_id)?;
+        let resize = ResizeWatch::new(&canvas)?;
+        let animation = AnimationLoop::new(listeners, frame).with_resize(resize);
+        animation.inner.borrow_mut().canvas = Some(canvas);
+        animation.start()?;
+        Ok(animation)
+    }
+
+    // keep a canvas fitted to the page while the loop runs
+    pub fn with_resize(self, resize: ResizeWatch) -> AnimationLoop {
+        self.inner.borrow_mut().resize = Some(resize);
+        self
+    }
+
+    // draw a frame now, as the browser does each time one comes round. a frame that
+    // fails stops the loop, and its error is handed back
+    pub fn step(&self) -> Result<(), ViewerError> {
+        run_frame(&self.inner)
+    }
+}
+
+#[wasm_bindgen]
+impl AnimationLoop {
+    pub fn start(&self) -> Result<(), ViewerError> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.running { return Ok(()); }
+        inner.listeners.attach()?;
+        if let Some(Err(e)) = inner.resize.as_mut().map(ResizeWatch::attach) {
+            inner.listeners.detach();
+            return Err(e);
+        }
+        inner.running = true;
+        request_frame(&self.inner, &mut inner);
+        Ok(())
+    }
+
+    // stop drawing and take the listeners off the page, `start` picks up where it left off
+    pub fn stop(&self) {
+        stop(&mut self.inner.borrow_mut());
+    }
+
+    pub fn running(&self) -> bool {
+        self.inner.borrow().running
+    }
+}
+
+impl Drop for AnimationLoop {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(canvas) = self.inner.borrow_mut().canvas.take() {
+            evict_gl_cache(&canvas);
+        }
+    }
+}
+
+fn stop(inner: &mut Inner) {
+    inner.running = false;
+    if let Some(id) = inner.requested.take() {
+        cancel_frame(id);
+    }
+    inner.listeners.detach();
+    if let Some(resize) = inner.resize.as_mut() {
+        resize.detach();
+    }
+}
+
+fn run_frame(rc: &Rc<RefCell<Inner>>) -> Result<(), ViewerError> {
+    let mut inner = rc.borrow_mut();
+    inner.requested = None;
+    if !inner.running { return Ok(()); }
+
+    if let Err(e) = (inner.frame)() {
+        stop(&mut inner);
+        return Err(e);
+    }
+    request_frame(rc, &mut inner);
+    Ok(())
+}
+
+// ask for the next frame with the loop's one closure, which only holds on to the
+// loop weakly so freeing the handle frees the closure too
+#[cfg(target_arch = "wasm32")]
+fn request_frame(rc: &Rc<RefCell<Inner>>, inner: &mut Inner) {
+    if inner.requested.is_some() { return; }
+    let Some(window) = web_sys::window() else { return; };
+
+    let weak = Rc::downgrade(rc);
+    let callback = inner.callback.get_or_insert_with(|| Closure::wrap(Box::new(move || {
+        let Some(rc) = weak.upgrade() else { return; };
+        if let Err(e) = run_frame(&rc) {
+            web_sys::console::error_1(&e.into());
+        }
+    })));
+    inner.requested = window.request_animation_frame(callback.as_ref().unchecked_ref()).ok();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn cancel_frame(id: i32) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.cancel_animation_frame(id);
+    }
+}
+
+// there are no animation frames outside the browser, `step` draws them instead
+#[cfg(not(target_arch = "wasm32"))]
+fn request_frame(_rc: &Rc<RefCell<Inner>>, _inner: &mut Inner) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn cancel_frame(_id: i32) {}
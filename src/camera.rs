@@ -0,0 +1,314 @@
+use std::f32::consts::PI;
+
+use web_sys::{HtmlElement, MouseEvent, WheelEvent};
+
+use crate::error::ViewerError;
+use crate::input::{self, Phase, PointerEvent};
+use crate::listeners::Listeners;
+use crate::math::{self, Mat4, Vec3};
+use crate::utils::get_element;
+
+// how a camera squashes the world it can see into clip space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // fov_y is the full height of the view, in radians
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    // height is how much of the world fits top to bottom
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } => math::perspective(fov_y, aspect, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let (half_width, half_height) = (height * aspect / 2.0, height / 2.0);
+                math::orthographic(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+
+    // world units per pixel at this distance from the camera, for panning
+    fn units_per_pixel(&self, distance: f32, element_height: f32) -> f32 {
+        match *self {
+            Projection::Perspective { fov_y, .. } => 2.0 * distance * (fov_y / 2.0).tan() / element_height,
+            Projection::Orthographic { height, .. } => height / element_height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub projection: Projection,
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+}
+
+impl Camera {
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Camera {
+        Camera {
+            projection: Projection::Perspective { fov_y, near, far },
+            eye: [0.0, 0.0, 1.0],
+            target: [0.0; 3],
+            up: [0.0, 1.0, 0.0],
+        }
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Camera {
+        Camera { projection: Projection::Orthographic { height, near, far }, ..Camera::perspective(1.0, near, far) }
+    }
+
+    pub fn looking_at(self, eye: Vec3, target: Vec3) -> Camera {
+        Camera { eye, target, ..self }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        math::look_at(self.eye, self.target, self.up)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        self.projection.matrix(aspect)
+    }
+
+    pub fn view_projection(&self, aspect: f32) -> Mat4 {
+        math::multiply(&self.projection(aspect), &self.view())
+    }
+
+    // the ray from the camera through a point in clip space, as an origin and a
+    // direction, for picking things under the pointer
+    pub fn ray(&self, clip: [f32; 2], aspect: f32) -> (Vec3, Vec3) {
+        let forward = math::normalize(math::sub(self.target, self.eye));
+        let right = math::normalize(math::cross(forward, self.up));
+        let up = math::cross(right, forward);
+        let across = |half_height: f32| {
+            math::add(math::scale(right, clip[0] * half_height * aspect), math::scale(up, clip[1] * half_height))
+        };
+
+        match self.projection {
+            Projection::Perspective { fov_y, .. } => {
+                (self.eye, math::normalize(math::add(forward, across((fov_y / 2.0).tan()))))
+            }
+            Projection::Orthographic { height, .. } => (math::add(self.eye, across(height / 2.0)), forward),
+        }
+    }
+}
+
+// what dragging the mouse does to the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrbitAction {
+    Rotate,
+    Pan,
+}
+
+// the smallest gap kept from straight up and straight down, where the view would flip
+const POLAR_EPSILON: f32 = 1e-4;
+
+// how much one notch of the wheel zooms by
+const ZOOM_STEP: f32 = 0.95;
+
+// swing a camera around its target, like three.js's OrbitControls
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitControls {
+    camera: Camera,
+
+    // how much of the remaining movement is taken each frame, 1 stops dead
+    pub damping: f32,
+    // radians down from straight above the target
+    pub min_polar_angle: f32,
+    pub max_polar_angle: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub enabled: bool,
+
+    // where the eye is around the target
+    azimuth: f32,
+    polar: f32,
+    distance: f32,
+
+    // movement still to be made
+    azimuth_delta: f32,
+    polar_delta: f32,
+    pan_delta: Vec3,
+    zoom_scale: f32,
+
+    // the current drag and where the mouse was last
+    action: Option<(OrbitAction, [f32; 2])>,
+}
+
+impl OrbitControls {
+    pub fn new(camera: Camera) -> OrbitControls {
+        let offset = math::sub(camera.eye, camera.target);
+        let distance = math::length(offset);
+        let polar = if distance > 0.0 { (offset[1] / distance).clamp(-1.0, 1.0).acos() } else { PI / 2.0 };
+
+        OrbitControls {
+            camera,
+            damping: 0.25,
+            min_polar_angle: 0.0,
+            max_polar_angle: PI,
+            min_distance: 0.0,
+            max_distance: f32::INFINITY,
+            enabled: true,
+            azimuth: offset[0].atan2(offset[2]),
+            polar,
+            distance,
+            azimuth_delta: 0.0,
+            polar_delta: 0.0,
+            pan_delta: [0.0; 3],
+            zoom_scale: 1.0,
+            action: None,
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn dragging(&self) -> bool {
+        self.action.is_some()
+    }
+
+    // start a drag at a position in pixels
+    pub fn pointer_down(&mut self, action: OrbitAction, x: f32, y: f32) {
+        if self.enabled {
+            self.action = Some((action, [x, y]));
+        }
+    }
+
+    // carry on a drag, element_height is the height of the canvas in pixels
+    pub fn pointer_move(&mut self, x: f32, y: f32, element_height: f32) {
+        let Some((action, last)) = self.action else { return; };
+        let (dx, dy) = (x - last[0], y - last[1]);
+        match action {
+            OrbitAction::Rotate => self.rotate(dx, dy, element_height),
+            OrbitAction::Pan => self.pan(dx, dy, element_height),
+        }
+        self.action = Some((action, [x, y]));
+    }
+
+    pub fn pointer_up(&mut self) {
+        self.action = None;
+    }
+
+    // dragging the full height of the canvas goes once around
+    pub fn rotate(&mut self, dx: f32, dy: f32, element_height: f32) {
+        if !self.enabled || element_height <= 0.0 { return; }
+        self.azimuth_delta -= 2.0 * PI * dx / element_height;
+        self.polar_delta -= 2.0 * PI * dy / element_height;
+    }
+
+    // slide the target across the ground so whatever was under the mouse stays there
+    pub fn pan(&mut self, dx: f32, dy: f32, element_height: f32) {
+        if !self.enabled || element_height <= 0.0 { return; }
+        let scale = self.camera.projection.units_per_pixel(self.distance, element_height);
+
+        let forward = math::sub(self.camera.target, self.camera.eye);
+        let right = math::normalize(math::cross(forward, self.camera.up));
+        let ahead = math::normalize(math::cross(self.camera.up, right));
+
+        let offset = math::add(math::scale(right, -dx * scale), math::scale(ahead, dy * scale));
+        self.pan_delta = math::add(self.pan_delta, offset);
+    }
+
+    // wheel up moves in, wheel down moves out
+    pub fn zoom(&mut self, delta_y: f32) {
+        if !self.enabled { return; }
+        if delta_y < 0.0 {
+            self.zoom_scale *= ZOOM_STEP;
+        } else if delta_y > 0.0 {
+            self.zoom_scale /= ZOOM_STEP;
+        }
+    }
+
+    // move the camera on by a frame, returns false once it has come to rest
+    pub fn update(&mut self) -> bool {
+        let before = self.camera;
+
+        self.azimuth += self.azimuth_delta * self.damping;
+        self.polar += self.polar_delta * self.damping;
+        self.polar = self.polar
+            .clamp(self.min_polar_angle, self.max_polar_angle)
+            .clamp(POLAR_EPSILON, PI - POLAR_EPSILON);
+
+        // an orthographic camera zooms by seeing less, not by moving closer
+        match &mut self.camera.projection {
+            Projection::Orthographic { height, .. } => *height *= self.zoom_scale,
+            Projection::Perspective { .. } => {
+                self.distance = (self.distance * self.zoom_scale).clamp(self.min_distance, self.max_distance);
+            }
+        }
+
+        let pan = math::scale(self.pan_delta, self.damping);
+        self.camera.target = math::add(self.camera.target, pan);
+
+        let offset = [
+            self.distance * self.polar.sin() * self.azimuth.sin(),
+            self.distance * self.polar.cos(),
+            self.distance * self.polar.sin() * self.azimuth.cos(),
+        ];
+        self.camera.eye = math::add(self.camera.target, offset);
+
+        // let the rest of the movement carry over to the next frames
+        let remaining = 1.0 - self.damping;
+        self.azimuth_delta *= remaining;
+        self.polar_delta *= remaining;
+        self.pan_delta = math::scale(self.pan_delta, remaining);
+        self.zoom_scale = 1.0;
+        if self.azimuth_delta.abs() < 1e-6 { self.azimuth_delta = 0.0; }
+        if self.polar_delta.abs() < 1e-6 { self.polar_delta = 0.0; }
+        if math::length(self.pan_delta) < 1e-6 { self.pan_delta = [0.0; 3]; }
+
+        self.camera != before
+    }
+}
+
+// left drag, a finger or a pen rotates, right drag or shift+left drag pans
+pub fn orbit_pointer(orbit: &mut OrbitControls, event: &PointerEvent) {
+    if !event.primary { return; }
+    let pointer = &event.pointer;
+    let [x, y] = pointer.offset;
+    match event.phase {
+        Phase::Down => {
+            let action = match pointer.button {
+                0 if pointer.shift || pointer.ctrl => OrbitAction::Pan,
+                0 => OrbitAction::Rotate,
+                2 => OrbitAction::Pan,
+                _ => return,
+            };
+            orbit.pointer_down(action, x, y);
+        }
+        Phase::Move if event.pressed => orbit.pointer_move(x, y, pointer.size[1]),
+        // a mouse hovering after a drag whose up was never seen
+        Phase::Move | Phase::Up => orbit.pointer_up(),
+    }
+}
+
+// run something against the orbit controls a demo keeps in its state
+pub type WithOrbit = fn(&mut dyn FnMut(&mut OrbitControls));
+
+// pointers rotate and pan, and the wheel zooms
+pub fn attach_orbit_controls(
+    listeners: &mut Listeners,
+    canvas_id: &str,
+    with_orbit: WithOrbit,
+) -> Result<(), ViewerError> {
+
+    // pointer capture keeps a drag going when it leaves the canvas
+    input::listen(listeners, canvas_id, move |event: &PointerEvent| with_orbit(&mut |orbit| orbit_pointer(orbit, event)))?;
+
+    let canvas: HtmlElement = get_element(canvas_id, "html element")?;
+
+    // the wheel zooms instead of scrolling the page
+    listeners.listen(&canvas, "wheel", move |event: WheelEvent| {
+        event.prevent_default();
+        with_orbit(&mut |orbit| orbit.zoom(event.delta_y() as f32));
+    })?;
+
+    // right drag pans, so keep the menu out of the way
+    listeners.listen(&canvas, "contextmenu", move |event: MouseEvent| {
+        event.prevent_default();
+    })?;
+
+    Ok(())
+}
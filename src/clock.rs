@@ -0,0 +1,149 @@
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+// the frame rate the demos were first tuned at, speeds written per frame are per one of these
+pub const TICK: f64 = 1.0 / 60.0;
+
+// a long pause, like a hidden tab, is played back as no more than this
+pub const MAX_FRAME_TIME: f64 = 0.25;
+
+// how far time scaling can speed up or slow down the demos
+pub const MIN_TIME_SCALE: f64 = 0.0;
+pub const MAX_TIME_SCALE: f64 = 10.0;
+
+thread_local! {
+    // shared by every demo on the page, so one control pauses them all
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+    static TIME_SCALE: Cell<f64> = const { Cell::new(1.0) };
+}
+
+// stop every demo's clock, frames still draw but nothing moves
+#[wasm_bindgen]
+pub fn pause() {
+    PAUSED.with(|paused| paused.set(true));
+}
+
+#[wasm_bindgen]
+pub fn resume() {
+    PAUSED.with(|paused| paused.set(false));
+}
+
+#[wasm_bindgen]
+pub fn is_paused() -> bool {
+    PAUSED.with(|paused| paused.get())
+}
+
+// run every demo at `scale` times its normal speed, anything that isn't a number is ignored
+#[wasm_bindgen]
+pub fn set_time_scale(scale: f64) {
+    if !scale.is_finite() { return; }
+    TIME_SCALE.with(|time_scale| time_scale.set(scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE)));
+}
+
+#[wasm_bindgen]
+pub fn time_scale() -> f64 {
+    TIME_SCALE.with(|time_scale| time_scale.get())
+}
+
+// milliseconds from some fixed point, the same clock as animation frame timestamps
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    web_sys::window().and_then(|window| window.performance()).map_or(0.0, |performance| performance.now())
+}
+
+// outside the browser, from the first time it's asked
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    thread_local! {
+        static START: std::time::Instant = std::time::Instant::now();
+    }
+    START.with(|start| start.elapsed().as_secs_f64() * 1000.0)
+}
+
+// one demo's view of time, how far it has moved on and by how much last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clock {
+    // the timestamp of the last frame, in milliseconds
+    last: Option<f64>,
+    delta: f64,
+    real_delta: f64,
+    elapsed: f64,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock::default()
+    }
+
+    // move on to a frame drawn now
+    pub fn tick(&mut self) -> f64 {
+        self.tick_at(now())
+    }
+
+    // move on to a frame at a timestamp in milliseconds, returns the scaled seconds since
+    // the last one. the first frame, and any while paused, take no time
+    pub fn tick_at(&mut self, now: f64) -> f64 {
+        let real = match self.last {
+            Some(last) if now > last => ((now - last) / 1000.0).min(MAX_FRAME_TIME),
+            _ => 0.0,
+        };
+        self.last = Some(now);
+
+        self.real_delta = real;
+        self.delta = if is_paused() { 0.0 } else { real * time_scale() };
+        self.elapsed += self.delta;
+        self.delta
+    }
+
+    // scaled seconds between the last two frames
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    // seconds between the last two frames, ignoring pausing and scaling, for things
+    // that follow the user rather than the animation
+    pub fn real_delta(&self) -> f64 {
+        self.real_delta
+    }
+
+    // scaled seconds since the first frame
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // the last frame in ticks, for speeds written per frame
+    pub fn delta_ticks(&self) -> f64 {
+        self.delta / TICK
+    }
+}
+
+// turns however long a frame took into a whole number of fixed steps, carrying
+// the remainder over to the next frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedStep {
+    pub step: f64,
+    accumulator: f64,
+}
+
+impl FixedStep {
+    pub fn new(step: f64) -> FixedStep {
+        FixedStep { step, accumulator: 0.0 }
+    }
+
+    // how many steps to run for a frame `elapsed` seconds long. long frames are
+    // already cut short by the clock before scaling, so a sped up demo keeps its speed
+    pub fn advance(&mut self, elapsed: f64) -> usize {
+        if !elapsed.is_finite() || elapsed <= 0.0 || self.step <= 0.0 { return 0; }
+        self.accumulator += elapsed;
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+        steps as usize
+    }
+}
+
+impl Default for FixedStep {
+    fn default() -> FixedStep {
+        FixedStep::new(TICK)
+    }
+}
@@ -1,12 +1,18 @@
-use std::cell::{RefCell, RefMut};
+use std::cell::RefCell;
 
 use wasm_bindgen::prelude::*;
-use web_sys::{window, HtmlElement, MouseEvent, WebGlRenderingContext};
+use web_sys::{HtmlElement, KeyboardEvent};
 use euclid::{self, Rect};
 
-extern crate js_sys;
-
-use crate::utils::default_gl;
+use crate::animation::AnimationLoop;
+use crate::error::ViewerError;
+use crate::history::{self, Command};
+use crate::input::{self, Phase, PointerEvent};
+use crate::listeners::Listeners;
+use crate::scene::{DragScene, SpaceScene, VertexScene};
+use crate::share::{self, Demo, Params};
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::utils::get_element;
 
 #[derive(Clone)]
 struct Space {
@@ -18,148 +24,775 @@ struct Draggable {
     rect: Rect<f32, f32>,
     colour: [f32; 3],
     hovered: bool,
-    id: i32,
+}
+
+// colours given to new verticies, in turn
+const VERTEX_COLOURS: [[f32; 3]; 4] = [
+    [1.0, 1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, 1.0, 0.0],
+];
+
+// a polygon always keeps at least a triangle
+const MIN_VERTICIES: usize = 3;
+
+// width and height of the handle on each vertex
+const VERTEX_SIZE: f32 = 0.1;
+
+// how far the arrow keys move a vertex, and with shift held
+const NUDGE_STEP: f32 = 0.01;
+const LARGE_NUDGE_STEP: f32 = 0.1;
+
+// the ring around the vertex the keyboard is on, apart from the white of hovering
+const FOCUS_COLOUR: [f32; 3] = [1.0, 0.55, 0.0];
+const FOCUS_MARGIN: f32 = 0.02;
+
+// the snap grid, from finer than a vertex handle up to one line across the whole canvas
+pub const DEFAULT_SNAP_SPACING: f32 = 0.1;
+pub const MIN_SNAP_SPACING: f32 = 0.01;
+pub const MAX_SNAP_SPACING: f32 = 2.0;
+
+// how close a dragged vertex has to come to another space's edge or centre to line up with it
+pub const SNAP_REACH: f32 = 0.03;
+
+// faint grid lines under the spaces, and brighter guides over them while lined up
+const GRID_COLOUR: [f32; 3] = [0.15, 0.15, 0.15];
+const GRID_WIDTH: f32 = 0.005;
+const GUIDE_COLOUR: [f32; 3] = [0.0, 0.6, 1.0];
+const GUIDE_WIDTH: f32 = 0.01;
+
+impl Draggable {
+    fn vertex(center: euclid::Point2D<f32, f32>, colour: [f32; 3]) -> Draggable {
+        let size = euclid::size2(VERTEX_SIZE, VERTEX_SIZE);
+        Draggable { rect: Rect::new(center - size / 2.0, size), colour, hovered: false }
+    }
+
+    fn center(&self) -> euclid::Point2D<f32, f32> {
+        self.rect.center()
+    }
+}
+
+impl Space {
+    // a box with its bottom left corner at x, y
+    fn rectangle(x: f32, y: f32, w: f32, h: f32) -> Space {
+        let corners = [(x, y), (x, y + h), (x + w, y + h), (x + w, y)];
+        Space {
+            verticies: corners.iter().zip(VERTEX_COLOURS)
+                .map(|((x, y), colour)| Draggable::vertex(euclid::point2(*x, *y), colour))
+                .collect(),
+        }
+    }
+
+    // the vertex under the cursor, if any
+    fn hit(&self, cursor: &Rect<f32, f32>) -> Option<usize> {
+        self.verticies.iter().position(|d| cursor.intersects(&d.rect))
+    }
+
+    fn points(&self) -> Vec<[f32; 2]> {
+        self.verticies.iter().map(|d| d.center().to_array()).collect()
+    }
+
+    // the smallest box around the centers of every vertex
+    fn bounds(&self) -> Rect<f32, f32> {
+        let min_x = self.verticies.iter().map(|d| d.center().x).fold(f32::INFINITY, f32::min);
+        let min_y = self.verticies.iter().map(|d| d.center().y).fold(f32::INFINITY, f32::min);
+        let max_x = self.verticies.iter().map(|d| d.center().x).fold(f32::NEG_INFINITY, f32::max);
+        let max_y = self.verticies.iter().map(|d| d.center().y).fold(f32::NEG_INFINITY, f32::max);
+        Rect::new(euclid::point2(min_x, min_y), euclid::size2(max_x - min_x, max_y - min_y))
+    }
+
+    // move one vertex, a box corner takes its neighbours with it to stay a box
+    fn place_vertex(&mut self, vertex: usize, center: euclid::Point2D<f32, f32>, polygon: bool) {
+        let Some(d) = self.verticies.get_mut(vertex) else { return; };
+        d.rect.origin = center - d.rect.size / 2.0;
+
+        // keep other verticies in line, polygons can take any shape
+        if polygon || self.verticies.len() != 4 { return; }
+        let origin = self.verticies[vertex].rect.origin;
+        if vertex.is_multiple_of(2) {
+            self.verticies[(vertex+3) % 4].rect.origin.y = origin.y;
+            self.verticies[(vertex+1) % 4].rect.origin.x = origin.x;
+        } else {
+            self.verticies[(vertex+3) % 4].rect.origin.x = origin.x;
+            self.verticies[(vertex+1) % 4].rect.origin.y = origin.y;
+        }
+    }
+
+    // the edge, named by its first vertex, closest to a point within `reach`
+    fn edge_near(&self, p: euclid::Point2D<f32, f32>, reach: f32) -> Option<usize> {
+        let points = self.points();
+        (0..points.len())
+            .map(|i| (i, distance_to_segment(p.to_array(), points[i], points[(i + 1) % points.len()])))
+            .filter(|(_, d)| *d <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
 }
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
 struct STATE {
     spaces: Vec<Space>,
     mouse_pos: euclid::Point2D<f32, f32>,
     mouse_down: bool,
     mouse_cursor: Draggable,
-    selected: Option<i32>,
+    // the space and vertex being dragged, and the space's verticies when it was picked up
+    selected: Option<(usize, usize)>,
+    drag_start: Option<Vec<Draggable>>,
+    // the space and vertex the keyboard moves, picked with tab or by clicking it
+    focused: Option<(usize, usize)>,
+    // only ring the focus once the keyboard is being used, like :focus-visible
+    focus_visible: bool,
+    polygon: bool,
+    // pointer drags land on the grid, or line up with the other spaces
+    snap: bool,
+    snap_spacing: f32,
+    // the x and y the dragged vertex has lined up with, drawn as guides
+    guides: [Option<f32>; 2],
+}
+
+impl STATE {
+    // the focused vertex, if it's still there after whatever has happened to the spaces
+    fn focus(&self) -> Option<(usize, usize)> {
+        self.focused.filter(|(space, vertex)| self.spaces.get(*space).is_some_and(|s| *vertex < s.verticies.len()))
+    }
+
+    // where a vertex of `space` dragged to `point` lands, and the guides it lined up with
+    fn snap(&self, space: usize, point: euclid::Point2D<f32, f32>) -> (euclid::Point2D<f32, f32>, [Option<f32>; 2]) {
+        if !self.snap { return (point, [None, None]); }
+
+        // the edges and centres of every other space
+        let (mut xs, mut ys) = (Vec::new(), Vec::new());
+        for (_, other) in self.spaces.iter().enumerate().filter(|(i, _)| *i != space) {
+            let bounds = other.bounds();
+            xs.extend([bounds.min_x(), bounds.center().x, bounds.max_x()]);
+            ys.extend([bounds.min_y(), bounds.center().y, bounds.max_y()]);
+        }
+
+        let (x, guide_x) = snap_axis(point.x, self.snap_spacing, &xs, SNAP_REACH);
+        let (y, guide_y) = snap_axis(point.y, self.snap_spacing, &ys, SNAP_REACH);
+        (euclid::point2(x, y), [guide_x, guide_y])
+    }
+}
+
+// line a value up with the closest target within reach, or failing that the nearest
+// grid line. returns where it lands, and the target if it lined up with one
+pub fn snap_axis(value: f32, spacing: f32, targets: &[f32], reach: f32) -> (f32, Option<f32>) {
+    let aligned = targets.iter().copied()
+        .filter(|target| (target - value).abs() <= reach)
+        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()));
+    match aligned {
+        Some(target) => (target, Some(target)),
+        None if spacing > 0.0 => ((value / spacing).round() * spacing, None),
+        None => (value, None),
+    }
 }
 
 // Initialize the state
 thread_local! {
     static STATE: RefCell<STATE> = RefCell::new(STATE {
-        spaces: vec![
-            Space {
-                verticies: vec![
-                    Draggable {
-                        rect: Rect::new(euclid::point2(-0.5, -0.5), euclid::size2(0.1, 0.1)),
-                        colour: [1.0, 1.0, 0.0],
-                        hovered: false,
-                        id: 0,
-                    },
-                    Draggable {
-                        rect: Rect::new(euclid::point2(-0.5, 0.5), euclid::size2(0.1, 0.1)),
-                        colour: [1.0, 0.0, 1.0],
-                        hovered: false,
-                        id: 1,
-                    },
-                    Draggable {
-                        rect: Rect::new(euclid::point2(0.5, 0.5), euclid::size2(0.1, 0.1)),
-                        colour: [0.0, 1.0, 1.0],
-                        hovered: false,
-                        id: 2,
-                    },
-                    Draggable {
-                        rect: Rect::new(euclid::point2(0.5, -0.5), euclid::size2(0.1, 0.1)),
-                        colour: [0.0, 1.0, 0.0],
-                        hovered: false,
-                        id: 3,
-                    },
-                ],
-            },
-        ],
+        spaces: vec![Space::rectangle(-0.45, -0.45, 1.0, 1.0)],
         mouse_cursor: Draggable {
             rect: Rect::new(euclid::point2(0.0, 0.0), euclid::size2(0.06, 0.06)),
             colour: [1.0, 0.0, 0.0],
             hovered: false,
-            id: 0,
         },
         mouse_pos: euclid::point2(0.0, 0.0),
         mouse_down: false,
         selected: None,
+        drag_start: None,
+        focused: None,
+        focus_visible: false,
+        polygon: false,
+        snap: false,
+        snap_spacing: DEFAULT_SNAP_SPACING,
+        guides: [None, None],
+    });
+
+}
+
+// a single vertex of a polygon was dragged
+struct MoveVertex {
+    space: usize,
+    vertex: usize,
+    from: euclid::Point2D<f32, f32>,
+    to: euclid::Point2D<f32, f32>,
+}
+
+// the verticies of a space changed together, from dragging a corner of a box
+// or adding and removing polygon verticies
+struct ResizeSpace {
+    space: usize,
+    from: Vec<Draggable>,
+    to: Vec<Draggable>,
+}
+
+struct AddSpace {
+    index: usize,
+    space: Space,
+}
+
+struct RemoveSpace {
+    index: usize,
+    space: Space,
+}
+
+fn move_vertex(space: usize, vertex: usize, center: euclid::Point2D<f32, f32>) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(d) = state.spaces.get_mut(space).and_then(|s| s.verticies.get_mut(vertex)) {
+            d.rect.origin = center - d.rect.size / 2.0;
+        }
+    });
+    share::update(Demo::Drag);
+}
+
+fn set_verticies(space: usize, verticies: &[Draggable]) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(s) = state.spaces.get_mut(space) {
+            s.verticies = verticies.to_vec();
+        }
+        state.selected = None;
     });
+    share::update(Demo::Drag);
+}
+
+fn insert_space(index: usize, space: Space) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let index = index.min(state.spaces.len());
+        state.spaces.insert(index, space);
+        state.selected = None;
+
+        // the keyboard stays on the same vertex
+        state.focused = state.focused.map(|(space, vertex)| (if space >= index { space + 1 } else { space }, vertex));
+    });
+    share::update(Demo::Drag);
+}
+
+fn take_space(index: usize) -> Option<Space> {
+    let space = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if index >= state.spaces.len() { return None; }
+
+        let shift = |selected: Option<(usize, usize)>| match selected {
+            Some((space, _)) if space == index => None,
+            Some((space, vertex)) if space > index => Some((space - 1, vertex)),
+            selected => selected,
+        };
+        state.selected = shift(state.selected);
+        state.focused = shift(state.focused);
+        Some(state.spaces.remove(index))
+    });
+    share::update(Demo::Drag);
+    space
+}
+
+impl Command for MoveVertex {
+    fn undo(&self) { move_vertex(self.space, self.vertex, self.from); }
+    fn redo(&self) { move_vertex(self.space, self.vertex, self.to); }
+}
+
+impl Command for ResizeSpace {
+    fn undo(&self) { set_verticies(self.space, &self.from); }
+    fn redo(&self) { set_verticies(self.space, &self.to); }
+}
 
+impl Command for AddSpace {
+    fn undo(&self) { take_space(self.index); }
+    fn redo(&self) { insert_space(self.index, self.space.clone()); }
 }
 
+impl Command for RemoveSpace {
+    fn undo(&self) { insert_space(self.index, self.space.clone()); }
+    fn redo(&self) { take_space(self.index); }
+}
+
+// let spaces take any shape, rather than keeping them rectangular
 #[wasm_bindgen]
-pub fn drag_init() {
-    
-    let document = window().unwrap().document().unwrap();
+pub fn drag_set_polygon(enabled: bool) {
+    STATE.with(|state| state.borrow_mut().polygon = enabled);
+    share::update(Demo::Drag);
+}
 
+#[wasm_bindgen]
+pub fn drag_polygon() -> bool {
+    STATE.with(|state| state.borrow().polygon)
+}
 
-    // do mouse input stuff
-    let canvas: HtmlElement = document.get_element_by_id("user_input").unwrap().dyn_into().unwrap();
-    let bounding_rect = canvas.get_bounding_client_rect();
+// add a box with its bottom left corner at x, y and return its index.
+// later spaces are drawn on top of earlier ones
+#[wasm_bindgen]
+pub fn drag_add_space(x: f32, y: f32, w: f32, h: f32) -> usize {
+    let space = Space::rectangle(x, y, w, h);
+    let index = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.spaces.push(space.clone());
+        state.spaces.len() - 1
+    });
+    history::record(AddSpace { index, space });
+    share::update(Demo::Drag);
+    index
+}
 
-    // mousemove
-    let closure: Closure<dyn FnMut(MouseEvent)> = Closure::wrap(Box::new(move |event: MouseEvent| {
-    
-        // update mouse position
-        let mouse_pos: euclid::Point2D<f32, f32> = euclid::point2(
-            -1.0 + 2.0 * (event.client_x() as f64 - bounding_rect.x()) as f32 / bounding_rect.width() as f32,
-            1.0 + -2.0 * (event.client_y() as f64 - bounding_rect.y()) as f32 / bounding_rect.height() as f32
-        );
+// remove a space, the ones above it move down an index
+#[wasm_bindgen]
+pub fn drag_remove_space(i: usize) -> bool {
+    match take_space(i) {
+        Some(space) => {
+            history::record(RemoveSpace { index: i, space });
+            true
+        }
+        None => false,
+    }
+}
+
+// snap pointer drags to a grid `spacing` apart and to the other spaces' edges and
+// centres. a spacing that isn't a positive number keeps the one already set
+#[wasm_bindgen]
+pub fn drag_set_snap(spacing: f32, enabled: bool) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if spacing.is_finite() && spacing > 0.0 {
+            state.snap_spacing = spacing.clamp(MIN_SNAP_SPACING, MAX_SNAP_SPACING);
+        }
+        state.snap = enabled;
+        state.guides = [None, None];
+    });
+    share::update(Demo::Drag);
+}
+
+#[wasm_bindgen]
+pub fn drag_snap_enabled() -> bool {
+    STATE.with(|state| state.borrow().snap)
+}
+
+#[wasm_bindgen]
+pub fn drag_snap_spacing() -> f32 {
+    STATE.with(|state| state.borrow().snap_spacing)
+}
+
+#[wasm_bindgen]
+pub fn drag_space_count() -> usize {
+    STATE.with(|state| state.borrow().spaces.len())
+}
+
+// the spaces as they are saved in a scene
+pub(crate) fn to_scene() -> DragScene {
+    STATE.with(|state| {
+        let state = state.borrow();
+        DragScene {
+            polygon: state.polygon,
+            spaces: state.spaces.iter().map(|space| SpaceScene {
+                vertices: space.verticies.iter().map(|d| VertexScene {
+                    origin: d.rect.origin.to_array(),
+                    size: d.rect.size.to_array(),
+                    colour: d.colour,
+                }).collect(),
+            }).collect(),
+        }
+    })
+}
+
+pub(crate) fn load_scene(scene: &DragScene) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.polygon = scene.polygon;
+        state.spaces = scene.spaces.iter().map(|space| Space {
+            verticies: space.vertices.iter().map(|v| Draggable {
+                rect: Rect::new(v.origin.into(), v.size.into()),
+                colour: v.colour,
+                hovered: false,
+            }).collect(),
+        }).collect();
+        state.selected = None;
+        state.drag_start = None;
+    });
+    share::update(Demo::Drag);
+}
+
+// the centers of every space's verticies, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    STATE.with(|state| {
+        let state = state.borrow();
+        let spaces: Vec<Vec<f32>> = state.spaces.iter()
+            .map(|space| space.points().concat())
+            .collect();
+        let mut params = Params::new();
+        params.set("polygon", share::flag(state.polygon));
+        params.set("snap", share::flag(state.snap));
+        params.set("snap_spacing", share::number(state.snap_spacing));
+        params.set("spaces", share::groups(&spaces));
+        params
+    })
+}
 
+pub(crate) fn load_params(params: &Params) {
+    if let Some(polygon) = params.flag("polygon") {
+        STATE.with(|state| state.borrow_mut().polygon = polygon);
+    }
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(snap) = params.flag("snap") {
+            state.snap = snap;
+        }
+        if let Some(spacing) = params.number::<f32>("snap_spacing").filter(|s| s.is_finite() && *s > 0.0) {
+            state.snap_spacing = spacing.clamp(MIN_SNAP_SPACING, MAX_SNAP_SPACING);
+        }
+    });
+
+    // every space needs whole points and at least a triangle, or none are loaded
+    let spaces = params.groups("spaces").filter(|spaces| {
+        spaces.iter().all(|s| s.len() % 2 == 0 && s.len() / 2 >= MIN_VERTICIES)
+    });
+    if let Some(spaces) = spaces {
         STATE.with(|state| {
             let mut state = state.borrow_mut();
+            state.spaces = spaces.iter().map(|points| Space {
+                verticies: points.chunks(2).enumerate()
+                    .map(|(i, p)| Draggable::vertex(euclid::point2(p[0], p[1]), VERTEX_COLOURS[i % VERTEX_COLOURS.len()]))
+                    .collect(),
+            }).collect();
+            state.selected = None;
+            state.drag_start = None;
+        });
 
-            // mouse move update state
-            state.mouse_pos = mouse_pos;
-            state.mouse_down = event.buttons() == 1;
-            state.mouse_cursor.rect.origin = mouse_pos - state.mouse_cursor.rect.size / 2.0;
-
-            // check if any draggable is hovered
-            let mut selected = state.selected.clone();
-            let mouse_cursor_box = state.mouse_cursor.rect.clone();
-
-            for space in state.spaces.iter_mut() {
-                for draggable in space.verticies.iter_mut() {
-                    draggable.hovered = false;
-                    // if intersects with mouse_cusor
-                    if mouse_cursor_box.intersects(&draggable.rect) {
-                        draggable.hovered = true;
-                        if event.buttons() == 1 {
-                            selected = Some(draggable.id);
-                        }
-                    }
+        // the history talks about spaces that no longer exist
+        history::clear_history();
+    }
+}
+
+// start the demo on the `user_input` canvas, the handle stops and restarts it
+#[wasm_bindgen]
+pub fn drag_init() -> Result<AnimationLoop, ViewerError> {
+
+    // the canvas takes the keyboard once it's clicked or tabbed to
+    let canvas: HtmlElement = get_element("user_input", "html element")?;
+    canvas.set_tab_index(0);
+
+    // mouse, touch and pen all drag the same way
+    let mut listeners = Listeners::new();
+    let focus_canvas = canvas.clone();
+    input::listen(&mut listeners, "user_input", move |event: &PointerEvent| {
+        if event.phase == Phase::Down {
+            let _ = focus_canvas.focus();
+        }
+        drag_pointer(event);
+    })?;
+
+    // arrows nudge, tab moves between verticies and delete removes
+    listeners.listen(&canvas, "keydown", move |event: KeyboardEvent| {
+        // leave ctrl+z and friends to the history
+        if event.ctrl_key() || event.meta_key() || event.alt_key() { return; }
+        if drag_key(&event.key(), event.shift_key()) {
+            event.prevent_default();
+        }
+    })?;
+
+    // ctrl+z and ctrl+shift+z step through the edits
+    history::listen_for_shortcuts(&mut listeners)?;
+
+    // pick up a shared layout
+    share::restore(Demo::Drag);
+
+    // start animation loop
+    let mut renderer = WebGlRenderer::for_canvas("user_input")?;
+    AnimationLoop::for_canvas("user_input", listeners, move || render(&mut renderer))
+}
+
+// only the first finger down drags, so a palm on the screen doesn't grab anything
+pub fn drag_pointer(event: &PointerEvent) {
+    if !event.primary { return; }
+    let [x, y] = event.pointer.position;
+    match event.phase {
+        // a touch has no hover before it, so pick up what's under it straight away
+        Phase::Down if event.pointer.button == 0 => {
+            drag_mouse_down(x, y, event.pointer.shift);
+            drag_mouse_move(x, y, true);
+        }
+        Phase::Down => {}
+        Phase::Move => drag_mouse_move(x, y, event.pressed),
+        Phase::Up => drag_mouse_move(x, y, false),
+    }
+}
 
+// move the cursor, hover and drag whatever is under it
+pub fn drag_mouse_move(x: f32, y: f32, pressed: bool) {
+    let mouse_pos: euclid::Point2D<f32, f32> = euclid::point2(x, y);
+    let letting_go = !pressed && STATE.with(|state| state.borrow().selected.is_some());
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+
+        // mouse move update state
+        state.mouse_pos = mouse_pos;
+        state.mouse_down = pressed;
+        state.mouse_cursor.rect.origin = mouse_pos - state.mouse_cursor.rect.size / 2.0;
+
+        // only the topmost vertex under the cursor is hovered
+        let mouse_cursor_box = state.mouse_cursor.rect;
+        let hit = state.spaces.iter().enumerate().rev()
+            .find_map(|(i, space)| space.hit(&mouse_cursor_box).map(|v| (i, v)));
+
+        for (i, space) in state.spaces.iter_mut().enumerate() {
+            for (v, draggable) in space.verticies.iter_mut().enumerate() {
+                draggable.hovered = hit == Some((i, v));
+            }
+        }
+
+        // hold on to a vertex until the button is let go
+        let was_selected = state.selected;
+        state.selected = match (pressed, state.selected) {
+            (false, _) => None,
+            (true, None) => hit,
+            (true, selected) => selected,
+        };
+
+        match (was_selected, state.selected) {
+            // remember where the space was when it was picked up, and put the keyboard on it
+            (None, Some((space, vertex))) => {
+                state.drag_start = Some(state.spaces[space].verticies.clone());
+                state.focused = Some((space, vertex));
+                state.focus_visible = false;
+            }
+            // pressing on nothing lets go of the keyboard focus
+            (None, None) if pressed => state.focused = None,
+            // and record the whole drag once it's let go
+            (Some((space, vertex)), None) => {
+                if let Some(from) = state.drag_start.take() {
+                    record_drag(&state, space, vertex, from);
                 }
             }
-            
-            if event.buttons() != 1 { selected = None; }
-
-            state.selected = selected;
-
-            // if selected
-            if let Some(selected) = state.selected {
-                // for each space
-                for space in state.spaces.iter_mut() {
-
-                    // move selected draggable        
-                    space.verticies[selected as usize].rect.origin = mouse_pos - space.verticies[selected as usize].rect.size / 2.0;
-
-                    // keep other verticies in line
-                    if selected % 2 == 0 {
-                        space.verticies[((selected+3) % 4) as usize].rect.origin.y = space.verticies[selected as usize].rect.origin.y;
-                        space.verticies[((selected+1) % 4) as usize].rect.origin.x = space.verticies[selected as usize].rect.origin.x;
-                    } else {
-                        space.verticies[((selected+3) % 4) as usize].rect.origin.x = space.verticies[selected as usize].rect.origin.x;
-                        space.verticies[((selected+1) % 4) as usize].rect.origin.y = space.verticies[selected as usize].rect.origin.y;
-                    }
+            _ => {}
+        }
+
+        // move selected draggable, lining it up when snapping
+        if let Some((space, selected)) = state.selected {
+            let (center, guides) = state.snap(space, mouse_pos);
+            let polygon = state.polygon;
+            state.spaces[space].place_vertex(selected, center, polygon);
+            state.guides = guides;
+        } else {
+            state.guides = [None, None];
+        }
 
+    });
+
+    // share the layout once a drag has finished
+    if letting_go {
+        share::update(Demo::Drag);
+    }
+}
+
+// a dragged polygon vertex moves on its own, a box corner takes its neighbours with it
+fn record_drag(state: &STATE, space: usize, vertex: usize, from: Vec<Draggable>) {
+    let Some(to) = state.spaces.get(space).map(|s| s.verticies.clone()) else { return; };
+    let moved = from.len() != to.len() || from.iter().zip(&to).any(|(a, b)| a.rect != b.rect);
+    if !moved { return; }
+
+    if state.polygon && from.len() == to.len() {
+        history::record(MoveVertex { space, vertex, from: from[vertex].center(), to: to[vertex].center() });
+    } else {
+        history::record(ResizeSpace { space, from, to });
+    }
+}
+
+// in polygon mode, clicking an edge adds a vertex there and removing clicks
+// take away the vertex under the cursor
+pub fn drag_mouse_down(x: f32, y: f32, remove: bool) {
+    let mouse_pos: euclid::Point2D<f32, f32> = euclid::point2(x, y);
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if !state.polygon { return; }
+
+        let mut cursor = state.mouse_cursor.rect;
+        cursor.origin = mouse_pos - cursor.size / 2.0;
+        let reach = cursor.size.width / 2.0;
+
+        // the topmost space under the cursor takes the click
+        for i in (0..state.spaces.len()).rev() {
+            let space = &mut state.spaces[i];
+
+            match space.hit(&cursor) {
+                Some(v) if remove => {
+                    if space.verticies.len() > MIN_VERTICIES {
+                        let from = space.verticies.clone();
+                        space.verticies.remove(v);
+                        let to = space.verticies.clone();
+                        history::record(ResizeSpace { space: i, from, to });
+                        state.selected = None;
+                    }
+                    return;
+                }
+                Some(_) => return,
+                None if remove => {}
+                None => {
+                    if let Some(edge) = space.edge_near(mouse_pos, reach) {
+                        let from = space.verticies.clone();
+                        let colour = VERTEX_COLOURS[space.verticies.len() % VERTEX_COLOURS.len()];
+                        space.verticies.insert(edge + 1, Draggable::vertex(mouse_pos, colour));
+                        let to = space.verticies.clone();
+                        history::record(ResizeSpace { space: i, from, to: to.clone() });
+
+                        // pick the new vertex straight up so it can be dragged out
+                        state.selected = Some((i, edge + 1));
+                        state.drag_start = Some(to);
+                        return;
+                    }
                 }
             }
-            
+        }
+    });
+    share::update(Demo::Drag);
+}
+
+// the keyboard, returns true if the key did something so the page shouldn't
+pub fn drag_key(key: &str, shift: bool) -> bool {
+    STATE.with(|state| state.borrow_mut().focus_visible = true);
+    let step = if shift { LARGE_NUDGE_STEP } else { NUDGE_STEP };
+    match key {
+        "ArrowLeft" => drag_nudge(-step, 0.0),
+        "ArrowRight" => drag_nudge(step, 0.0),
+        "ArrowUp" => drag_nudge(0.0, step),
+        "ArrowDown" => drag_nudge(0.0, -step),
+        "Tab" => drag_focus_next(shift),
+        "Delete" | "Backspace" => drag_delete_focused(),
+        "Escape" => STATE.with(|state| state.borrow_mut().focused.take().is_some()),
+        _ => false,
+    }
+}
+
+// move the focused vertex, a box corner takes its neighbours with it like a drag does
+pub fn drag_nudge(dx: f32, dy: f32) -> bool {
+    let nudged = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some((space, vertex)) = state.focus() else { return false; };
+        let polygon = state.polygon;
+        let from = state.spaces[space].verticies.clone();
+        state.spaces[space].place_vertex(vertex, from[vertex].center() + euclid::vec2(dx, dy), polygon);
+        record_drag(&state, space, vertex, from);
+        true
+    });
+    if nudged {
+        share::update(Demo::Drag);
+    }
+    nudged
+}
+
+// put the keyboard on the next vertex, going through every space in turn. it runs
+// off the end with nothing focused and returns false, so tab can leave the canvas
+pub fn drag_focus_next(backwards: bool) -> bool {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let order: Vec<(usize, usize)> = state.spaces.iter().enumerate()
+            .flat_map(|(i, space)| (0..space.verticies.len()).map(move |v| (i, v)))
+            .collect();
+        let current = state.focus().and_then(|focus| order.iter().position(|o| *o == focus));
+        state.focused = match (current, backwards) {
+            (None, false) => order.first().copied(),
+            (None, true) => order.last().copied(),
+            (Some(i), false) => order.get(i + 1).copied(),
+            (Some(i), true) => i.checked_sub(1).map(|i| order[i]),
+        };
+        state.focused.is_some()
+    })
+}
+
+// take away the focused vertex of a polygon, or the whole box it's a corner of
+pub fn drag_delete_focused() -> bool {
+    let Some((space, vertex)) = STATE.with(|state| state.borrow().focus()) else { return false; };
+    if !STATE.with(|state| state.borrow().polygon) {
+        return drag_remove_space(space);
+    }
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let verticies = &mut state.spaces[space].verticies;
+        if verticies.len() <= MIN_VERTICIES { return; }
+
+        let from = verticies.clone();
+        verticies.remove(vertex);
+        let to = verticies.clone();
+
+        // carry on from the vertex that took its place
+        state.focused = Some((space, vertex % to.len()));
+        state.selected = None;
+        history::record(ResizeSpace { space, from, to });
+    });
+    share::update(Demo::Drag);
+    true
+}
+
+// the space and vertex the keyboard is on, if any
+pub fn drag_focus() -> Option<(usize, usize)> {
+    STATE.with(|state| state.borrow().focus())
+}
+
+// shortest distance from a point to the segment between a and b
+fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let length = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length > 0.0 { ((ap[0] * ab[0] + ap[1] * ab[1]) / length).clamp(0.0, 1.0) } else { 0.0 };
+    let closest = [a[0] + ab[0] * t, a[1] + ab[1] * t];
+    ((p[0] - closest[0]).powi(2) + (p[1] - closest[1]).powi(2)).sqrt()
+}
+
+// twice the signed area of a triangle, positive when counter clockwise
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// split a simple polygon into triangles by ear clipping
+pub fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::new();
+    if points.len() < 3 { return triangles; }
+
+    // walk the outline counter clockwise
+    let area: f32 = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum();
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if area < 0.0 { remaining.reverse(); }
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+
+            // an ear is convex and has no other corner inside it
+            cross(points[a], points[b], points[c]) > 0.0
+                && remaining.iter()
+                    .filter(|&&p| p != a && p != b && p != c)
+                    .all(|&p| !inside_triangle(points[p], points[a], points[b], points[c]))
         });
-        
 
-    }));
-    canvas.add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref()).unwrap();
-    closure.forget(); // Keep the closure alive
+        // self intersecting outlines have no ears left, fan what remains
+        let Some(i) = ear else { break; };
 
-    // start animation loop
-    user_draw(default_gl());
+        triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
 
+fn inside_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
 }
 
 
-fn draw_draggable(gl: WebGlRenderingContext, mut d: Draggable) {
+fn draw_draggable(renderer: &mut impl Renderer, mut d: Draggable) -> Result<(), ViewerError> {
 
     let rect = d.rect;
 
@@ -172,65 +805,102 @@ fn draw_draggable(gl: WebGlRenderingContext, mut d: Draggable) {
     if d.hovered { d.colour = [1.0, 1.0, 1.0] }
 
     let data = vec![
-        top_left.x as f32, top_left.y as f32, d.colour[0], d.colour[1], d.colour[2],
-        bottom_left.x as f32, bottom_left.y as f32, d.colour[0], d.colour[1], d.colour[2],
-        bottom_right.x as f32, bottom_right.y as f32, d.colour[0], d.colour[1], d.colour[2],
-        top_right.x as f32, top_right.y as f32, d.colour[0], d.colour[1], d.colour[2], 
+        top_left.x, top_left.y, d.colour[0], d.colour[1], d.colour[2],
+        bottom_left.x, bottom_left.y, d.colour[0], d.colour[1], d.colour[2],
+        bottom_right.x, bottom_right.y, d.colour[0], d.colour[1], d.colour[2],
+        top_right.x, top_right.y, d.colour[0], d.colour[1], d.colour[2], 
     ];
 
-    // fill ARRAY_BUFFER with the vertex data
-    gl.buffer_data_with_array_buffer_view(
-        WebGlRenderingContext::ARRAY_BUFFER,
-        &(unsafe { js_sys::Float32Array::view(&data).into() }),
-        WebGlRenderingContext::STATIC_DRAW,
-    );
-    gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, 4);
+    renderer.draw_vertices(Primitive::TriangleFan, &data)
 }
 
 
-fn draw_space(gl: WebGlRenderingContext, space: Space) {
-
-    // find min and max x and y values
-    let min_x = space.verticies.iter().map(|d| d.rect.origin.x + d.rect.size.width / 2.0).fold(f32::INFINITY, f32::min);
-    let min_y = space.verticies.iter().map(|d| d.rect.origin.y + d.rect.size.height / 2.0).fold(f32::INFINITY, f32::min);
-    let max_x = space.verticies.iter().map(|d| d.rect.origin.x + d.rect.size.width / 2.0).fold(f32::NEG_INFINITY, f32::max);
-    let max_y = space.verticies.iter().map(|d| d.rect.origin.y + d.rect.size.height / 2.0).fold(f32::NEG_INFINITY, f32::max);
+fn draw_space(
+    renderer: &mut impl Renderer,
+    space: &Space,
+    polygon: bool,
+    focused: Option<usize>,
+) -> Result<(), ViewerError> {
+
+    if polygon {
+        // fill the outline itself
+        let points = space.points();
+        let data: Vec<f32> = points.iter().flat_map(|p| [p[0], p[1], 0.3, 0.3, 0.3]).collect();
+        let indices: Vec<u32> = triangulate(&points).into_iter().flatten().map(|i| i as u32).collect();
+        renderer.upload(&data)?;
+        renderer.draw_indexed(Primitive::Triangles, &indices)?;
+    } else {
+        // draw square
+        draw_draggable(
+            renderer,
+            Draggable {
+                rect: space.bounds(),
+                colour: [0.3, 0.3, 0.3],
+                hovered: false,
+            }
+        )?;
+    }
 
-    // draw square
-    draw_draggable(
-        gl.clone(), 
-        Draggable {
-            rect: Rect::new(euclid::point2(min_x, min_y), euclid::size2(max_x - min_x, max_y - min_y)),
-            colour: [0.3, 0.3, 0.3],
+    // a ring around the focused vertex, drawn under it
+    if let Some(d) = focused.and_then(|v| space.verticies.get(v)) {
+        draw_draggable(renderer, Draggable {
+            rect: d.rect.inflate(FOCUS_MARGIN, FOCUS_MARGIN),
+            colour: FOCUS_COLOUR,
             hovered: false,
-            id: 10,
-        }
-    );
+        })?;
+    }
 
     for draggable in space.verticies.iter() {
-        draw_draggable(gl.clone(), draggable.clone());
+        draw_draggable(renderer, draggable.clone())?;
     }
+    Ok(())
 }
 
-fn user_draw(gl: WebGlRenderingContext) {
+fn draw_line(renderer: &mut impl Renderer, rect: Rect<f32, f32>, colour: [f32; 3]) -> Result<(), ViewerError> {
+    draw_draggable(renderer, Draggable { rect, colour, hovered: false })
+}
 
-    gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+// a line every `spacing` across the canvas, out from the middle
+fn draw_grid(renderer: &mut impl Renderer, spacing: f32) -> Result<(), ViewerError> {
+    let count = (1.0 / spacing).floor() as i32;
+    for i in -count..=count {
+        let at = i as f32 * spacing - GRID_WIDTH / 2.0;
+        draw_line(renderer, Rect::new(euclid::point2(at, -1.0), euclid::size2(GRID_WIDTH, 2.0)), GRID_COLOUR)?;
+        draw_line(renderer, Rect::new(euclid::point2(-1.0, at), euclid::size2(2.0, GRID_WIDTH)), GRID_COLOUR)?;
+    }
+    Ok(())
+}
+
+// draw every space and the mouse cursor
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
+
+    renderer.clear([0.0, 0.0, 0.0, 0.0]);
     
     STATE.with(|state| {
-        let state: RefMut<STATE> = state.borrow_mut();
+        let state = state.borrow();
 
-        // draw spaces
-        for space in state.spaces.iter() {
-            draw_space(gl.clone(), space.clone());
+        // the grid goes under everything
+        if state.snap {
+            draw_grid(renderer, state.snap_spacing)?;
         }
 
-        // draw mouse cursor 
-        draw_draggable(gl.clone(), state.mouse_cursor.clone());
-    });
+        // draw spaces
+        let focus = state.focus().filter(|_| state.focus_visible);
+        for (i, space) in state.spaces.iter().enumerate() {
+            let focused = focus.filter(|(space, _)| *space == i).map(|(_, vertex)| vertex);
+            draw_space(renderer, space, state.polygon, focused)?;
+        }
 
-    // request for another animation frame
-    let closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || { user_draw(gl.clone()); }));
-    web_sys::window().unwrap().request_animation_frame(closure.as_ref().unchecked_ref()).unwrap();
-    closure.forget();
+        // what the dragged vertex lined up with
+        let [guide_x, guide_y] = state.guides;
+        if let Some(x) = guide_x {
+            draw_line(renderer, Rect::new(euclid::point2(x - GUIDE_WIDTH / 2.0, -1.0), euclid::size2(GUIDE_WIDTH, 2.0)), GUIDE_COLOUR)?;
+        }
+        if let Some(y) = guide_y {
+            draw_line(renderer, Rect::new(euclid::point2(-1.0, y - GUIDE_WIDTH / 2.0), euclid::size2(2.0, GUIDE_WIDTH)), GUIDE_COLOUR)?;
+        }
 
+        // draw mouse cursor 
+        draw_draggable(renderer, state.mouse_cursor.clone())
+    })
 }
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+// which stage of the pipeline a shader belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl ShaderStage {
+    pub fn from_gl(shader_type: u32) -> ShaderStage {
+        if shader_type == WebGlRenderingContext::VERTEX_SHADER {
+            ShaderStage::Vertex
+        } else {
+            ShaderStage::Fragment
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+// everything that can go wrong while setting up a demo
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewerError {
+    MissingCanvas(String),
+    MissingElement(String),
+    WrongElementType { id: String, expected: &'static str },
+    ContextUnavailable(String),
+    Listener(String),
+    ShaderCompile { stage: ShaderStage, log: String },
+    Link(String),
+    InvalidScene(String),
+    InvalidColor(String),
+}
+
+impl ViewerError {
+    // short machine readable name, exposed to js as `kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ViewerError::MissingCanvas(_) => "missing_canvas",
+            ViewerError::MissingElement(_) => "missing_element",
+            ViewerError::WrongElementType { .. } => "wrong_element_type",
+            ViewerError::ContextUnavailable(_) => "context_unavailable",
+            ViewerError::Listener(_) => "listener",
+            ViewerError::ShaderCompile { .. } => "shader_compile",
+            ViewerError::Link(_) => "link",
+            ViewerError::InvalidScene(_) => "invalid_scene",
+            ViewerError::InvalidColor(_) => "invalid_color",
+        }
+    }
+}
+
+impl fmt::Display for ViewerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ViewerError::MissingCanvas(id) => write!(f, "no canvas with id '{}'", id),
+            ViewerError::MissingElement(id) => write!(f, "no element with id '{}'", id),
+            ViewerError::WrongElementType { id, expected } => {
+                write!(f, "element '{}' is not a {}", id, expected)
+            }
+            ViewerError::ContextUnavailable(what) => write!(f, "{} context unavailable", what),
+            ViewerError::Listener(event) => write!(f, "could not listen for {} events", event),
+            ViewerError::ShaderCompile { stage, log } => {
+                write!(f, "error compiling {} shader: {}", stage, log)
+            }
+            ViewerError::Link(log) => write!(f, "error linking shader program: {}", log),
+            ViewerError::InvalidScene(why) => write!(f, "invalid scene: {}", why),
+            ViewerError::InvalidColor(why) => write!(f, "invalid color: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ViewerError {}
+
+// thrown into js as an Error with an extra `kind` field
+impl From<ViewerError> for JsValue {
+    fn from(error: ViewerError) -> JsValue {
+        let js_error = js_sys::Error::new(&error.to_string());
+        let _ = js_sys::Reflect::set(&js_error, &"kind".into(), &error.kind().into());
+        js_error.into()
+    }
+}
@@ -1,18 +1,28 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlRenderingContext, WebGlProgram};
+use web_sys::WebGlRenderingContext;
 use palette::{Hsv, Srgb, FromColor};
 use std::cell::RefCell;
 
-extern crate js_sys;
-
 use std::f64::consts::PI;
-use crate::utils::{init_webgl_context, link_shaders};
+use crate::animation::AnimationLoop;
+use crate::clock::Clock;
+use crate::error::ViewerError;
+use crate::listeners::Listeners;
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::share::{self, Demo, Params};
+
+// the sides the slider offers, a link asking for anything else is ignored
+const MIN_SIDES: i32 = 3;
+const MAX_SIDES: i32 = 20;
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 struct STATE {
     side_count: i32,
     vertices: Vec<Vertex>,
-    time: i32,
+    // in ticks, so the speed below is per frame at 60 frames a second
+    time: f64,
+    clock: Clock,
     rotate_speed: i32,
 }
 
@@ -28,7 +38,8 @@ thread_local! {
     static STATE: RefCell<STATE> = RefCell::new(STATE {
         side_count: 8,
         vertices: get_coords_of_ngon(8),
-        time: 0,
+        time: 0.0,
+        clock: Clock::new(),
         rotate_speed: 5,
     });
 }
@@ -41,6 +52,18 @@ pub fn g_update_sides(n: i32) {
         state.vertices = get_coords_of_ngon(n);
         state.side_count = n;
     });
+    share::update(Demo::Gradient);
+}
+
+// side count, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    let mut params = Params::new();
+    params.set("sides", STATE.with(|state| state.borrow().side_count).to_string());
+    params
+}
+
+pub(crate) fn load_params(params: &Params) {
+    if let Some(n) = params.number::<i32>("sides").filter(|n| (MIN_SIDES..=MAX_SIDES).contains(n)) { g_update_sides(n); }
 }
 
 // generate the coordinates of an n-gon using roots of unity/ polar coords
@@ -70,104 +93,65 @@ fn rotate_2d_coords(coords: Vec<Vertex>, angle: f32) -> Vec<Vertex> {
 
 // draw the state to the screen given 
 #[wasm_bindgen]
-pub fn gradient_draw(canvas_id: &str) -> Result<WebGlRenderingContext, JsValue> {
+pub fn gradient_draw(canvas_id: &str) -> Result<WebGlRenderingContext, ViewerError> {
 
     // create gl context and shader program
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
-    
-    let vertex_shader_source =
-        "
-        attribute vec2 coordinates;
-        attribute vec3 colour;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_Position = vec4(coordinates, 0.0, 1.0);
-            out_colour = colour;
-        }
-        ";
-    let fragment_shader_source = 
-        "
-        precision mediump float;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_FragColor = vec4(out_colour, 1.0);
-        }
-        ";
-
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
-
-    // spawn the ARRAY_BUFFER for the vertices to use each frame
-    gl.bind_buffer(
-        WebGlRenderingContext::ARRAY_BUFFER, 
-        Some(&gl.create_buffer().unwrap())
-    );
-
-    // specify how the coordinates attribute should read from the vertex buffer
-    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates") as u32;
-    gl.vertex_attrib_pointer_with_i32(
-        coordinates_location, 
-        2, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        0
-    );
-    gl.enable_vertex_attrib_array(coordinates_location);
-
-    // specify how the colour attribute should read from from the vertex buffer
-    let colour_location = gl.get_attrib_location(&shader_program, "colour") as u32; 
-    gl.vertex_attrib_pointer_with_i32(
-        colour_location, 
-        3, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        2 * std::mem::size_of::<f32>() as i32,
-    );
-    gl.enable_vertex_attrib_array(colour_location);
+    let mut renderer = WebGlRenderer::for_canvas(canvas_id)?;
+
+    // the same speed whatever the frame rate
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.clock.tick();
+        state.time += state.clock.delta_ticks();
+    });
+    render(&mut renderer)?;
 
+    Ok(renderer.gl().clone())
+}
+
+// draw the demo every frame until the handle stops it
+#[wasm_bindgen]
+pub fn gradient_init(canvas_id: &str) -> Result<AnimationLoop, ViewerError> {
+
+    // pick up a shared state when the demo starts
+    share::restore(Demo::Gradient);
+
+    let id = canvas_id.to_owned();
+    AnimationLoop::for_canvas(canvas_id, Listeners::new(), move || gradient_draw(&id).map(|_| ()))
+}
+
+// draw the hue wheel at the current time
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
+
+    // keep the wheel round on a canvas that isn't square
+    let viewport = renderer.viewport();
 
     STATE.with(|state: &RefCell<STATE>| {
 
-        // get a mutable version state 
-        let mut state: std::cell::RefMut<STATE> = state.borrow_mut();
-        
-        state.time += 1; 
+        // get the state for this program
+        let state: std::cell::Ref<STATE> = state.borrow();
 
         let rotated_verts: Vec<Vertex> = rotate_2d_coords(
             state.vertices.clone(), 
-            state.time as f32 * state.rotate_speed as f32 * 0.001
+            (state.time * state.rotate_speed as f64 * 0.001) as f32
         );
 
         let vertex_colours: Vec<Srgb> = get_coords_of_ngon(state.side_count).iter()
             .map(|c| {
                 let hue = 255.0 * c.theta / (2.0 * PI) as f32;
                 let hsv = Hsv::new(hue, 1.0, 1.0);
-                Srgb::from_color(hsv).into()
+                Srgb::from_color(hsv)
             }).collect();
 
         // zip the two lists together
         let data: Vec<f32> = rotated_verts.iter()
             .zip(vertex_colours.iter())
-            .flat_map(|(v, c)| vec![v.x, v.y, c.red, c.green, c.blue])
+            .map(|(v, c)| (viewport.world_to_clip([v.x, v.y]), c))
+            .flat_map(|(v, c)| vec![v[0], v[1], c.red, c.green, c.blue])
             .collect::<Vec<f32>>();
 
-        
-        // fill ARRAY_BUFFER with the vertex data
-        gl.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ARRAY_BUFFER,
-            &(unsafe { js_sys::Float32Array::view(&data).into() }),
-            WebGlRenderingContext::STATIC_DRAW,
-        );
-       
         // draw on the screen
-        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
-        let vertices_count = (state.vertices.len()) as i32;
-        gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, vertices_count);
-
-    });
-
-    Ok(gl)
+        renderer.clear([0.0, 0.0, 0.0, 0.0]);
+        renderer.draw_vertices(Primitive::TriangleFan, &data)
+    })
 }
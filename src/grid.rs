@@ -0,0 +1,114 @@
+use palette::Srgb;
+
+use crate::renderer::VERTEX_STRIDE;
+
+// a square grid covering clip space, `resolution` cells along each edge.
+// each corner is stored once and the squares index into them
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMesh {
+    resolution: i32,
+    corners: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+// return the grid coordinates of two triangles that make a square
+fn make_square(x: i32, y: i32) -> [(i32, i32); 6] {
+    [
+        (x, y),
+        (x + 1, y),
+        (x, y + 1),
+        (x, y + 1),
+        (x + 1, y),
+        (x + 1, y + 1),
+    ]
+}
+
+impl GridMesh {
+    pub fn new(resolution: i32) -> GridMesh {
+        let mut grid = GridMesh { resolution, corners: Vec::new(), indices: Vec::new() };
+
+        // every corner of the grid, mapped onto range -1 to 1
+        let side = grid.resolution + 1;
+        grid.corners = (0..side)
+            .flat_map(|x| (0..side).map(move |y| (x, y)))
+            .flat_map(|(x, y)| [grid.to_clip(x), grid.to_clip(y)])
+            .collect();
+
+        // the corner used by each vertex of each square
+        grid.indices = grid.cells()
+            .flat_map(|(x, y)| make_square(x, y))
+            .map(|(x, y)| (x * side + y) as u32)
+            .collect();
+
+        grid
+    }
+
+    // cells along each edge of the grid
+    pub fn resolution(&self) -> i32 {
+        self.resolution
+    }
+
+    // width of one cell in clip space
+    pub fn cell_size(&self) -> f32 {
+        2.0 / self.resolution as f32
+    }
+
+    // map a grid coordinate onto range -1 to 1
+    pub fn to_clip(&self, v: i32) -> f32 {
+        (v as f32 / self.resolution as f32) * 2.0 - 1.0
+    }
+
+    // the grid coordinates of every cell, in the order they are drawn
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32)> {
+        let side = self.resolution;
+        (0..side).flat_map(move |x| (0..side).map(move |y| (x, y)))
+    }
+
+    pub fn cell_count(&self) -> usize {
+        (self.resolution * self.resolution) as usize
+    }
+
+    // x, y of every corner
+    pub fn corners(&self) -> &[f32] {
+        &self.corners
+    }
+
+    pub fn corner_count(&self) -> usize {
+        self.corners.len() / 2
+    }
+
+    pub fn corner(&self, index: usize) -> [f32; 2] {
+        [self.corners[index * 2], self.corners[index * 2 + 1]]
+    }
+
+    // six corner indices per cell, for drawing triangles
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    // interleaved vertices with one colour per corner, shared by every cell
+    // that touches it. draw with `indices`
+    pub fn shared_vertices(&self, mut colour: impl FnMut(usize, [f32; 2]) -> Srgb) -> Vec<f32> {
+        let mut data = Vec::with_capacity(self.corner_count() * VERTEX_STRIDE);
+        for i in 0..self.corner_count() {
+            let p = self.corner(i);
+            let c = colour(i, p);
+            data.extend([p[0], p[1], c.red, c.green, c.blue]);
+        }
+        data
+    }
+
+    // interleaved vertices with six of their own per cell, so every vertex can
+    // be coloured separately. the closure gets the cell and the vertex within it
+    pub fn cell_vertices(&self, mut colour: impl FnMut(usize, usize, [f32; 2]) -> Srgb) -> Vec<f32> {
+        let mut data = Vec::with_capacity(self.indices.len() * VERTEX_STRIDE);
+        for (cell, square) in self.indices.chunks_exact(6).enumerate() {
+            for (vertex, corner) in square.iter().enumerate() {
+                let p = self.corner(*corner as usize);
+                let c = colour(cell, vertex, p);
+                data.extend([p[0], p[1], c.red, c.green, c.blue]);
+            }
+        }
+        data
+    }
+}
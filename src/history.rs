@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlElement, HtmlInputElement, KeyboardEvent};
+
+use crate::error::ViewerError;
+use crate::listeners::Listeners;
+
+// how many edits can be taken back
+pub const HISTORY_LIMIT: usize = 100;
+
+// an edit to a demo that has already been made, and can be taken back
+pub trait Command {
+    fn undo(&self);
+    fn redo(&self);
+}
+
+struct History {
+    done: VecDeque<Box<dyn Command>>,
+    undone: Vec<Box<dyn Command>>,
+}
+
+thread_local! {
+    static HISTORY: RefCell<History> = RefCell::new(History {
+        done: VecDeque::new(),
+        undone: Vec::new(),
+    });
+}
+
+// remember an edit, forgetting anything that was undone before it
+pub fn record(command: impl Command + 'static) {
+    HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        history.undone.clear();
+        history.done.push_back(Box::new(command));
+        if history.done.len() > HISTORY_LIMIT {
+            history.done.pop_front();
+        }
+    });
+}
+
+// forget every edit, for when the demo it belongs to starts again
+pub fn clear_history() {
+    HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        history.done.clear();
+        history.undone.clear();
+    });
+}
+
+// take back the last edit, returns false if there was nothing to undo
+#[wasm_bindgen]
+pub fn undo() -> bool {
+    // the history isn't borrowed while the command changes its demo
+    let Some(command) = HISTORY.with(|history| history.borrow_mut().done.pop_back()) else {
+        return false;
+    };
+    command.undo();
+    HISTORY.with(|history| history.borrow_mut().undone.push(command));
+    true
+}
+
+// make the last undone edit again, returns false if there was nothing to redo
+#[wasm_bindgen]
+pub fn redo() -> bool {
+    let Some(command) = HISTORY.with(|history| history.borrow_mut().undone.pop()) else {
+        return false;
+    };
+    command.redo();
+    HISTORY.with(|history| history.borrow_mut().done.push_back(command));
+    true
+}
+
+#[wasm_bindgen]
+pub fn can_undo() -> bool {
+    HISTORY.with(|history| !history.borrow().done.is_empty())
+}
+
+#[wasm_bindgen]
+pub fn can_redo() -> bool {
+    HISTORY.with(|history| !history.borrow().undone.is_empty())
+}
+
+// somewhere the user types, where ctrl+z belongs to the text. sliders and
+// checkboxes have nothing to undo, so the demo keeps the shortcut on those
+fn editable(element: &HtmlElement) -> bool {
+    if let Some(input) = element.dyn_ref::<HtmlInputElement>() {
+        return !matches!(input.type_().as_str(), "checkbox" | "radio" | "range" | "button" | "submit" | "reset" | "color" | "file");
+    }
+    element.tag_name() == "TEXTAREA" || element.is_content_editable()
+}
+
+// ctrl+z undoes and ctrl+shift+z redoes, anywhere on the page but text fields, for as long as the
+// demo's listeners are attached
+pub fn listen_for_shortcuts(listeners: &mut Listeners) -> Result<(), ViewerError> {
+    let window = web_sys::window().ok_or_else(|| ViewerError::ContextUnavailable("window".into()))?;
+    listeners.listen(&window, "keydown", move |event: KeyboardEvent| {
+        if !(event.ctrl_key() || event.meta_key()) || !event.key().eq_ignore_ascii_case("z") {
+            return;
+        }
+        // text fields on the page keep the browser's own undo
+        if event.target().and_then(|target| target.dyn_into::<HtmlElement>().ok()).is_some_and(|element| editable(&element)) {
+            return;
+        }
+        event.prevent_default();
+        if event.shift_key() { redo(); } else { undo(); }
+    })
+}
@@ -0,0 +1,215 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use web_sys::{HtmlElement, PointerEvent as DomPointerEvent};
+
+use crate::error::ViewerError;
+use crate::listeners::Listeners;
+use crate::utils::get_element;
+use crate::viewport::client_to_clip;
+
+// how far a pressed pointer has to move, in css pixels, before it counts as a drag
+pub const DRAG_THRESHOLD: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerKind {
+    Mouse,
+    Touch,
+    Pen,
+}
+
+impl PointerKind {
+    pub fn from_name(name: &str) -> PointerKind {
+        match name {
+            "touch" => PointerKind::Touch,
+            "pen" => PointerKind::Pen,
+            _ => PointerKind::Mouse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Down,
+    Move,
+    // released, or taken away by the browser
+    Up,
+}
+
+// one mouse, finger or pen, wherever it is on the canvas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pointer {
+    pub id: i32,
+    pub kind: PointerKind,
+    // -1 to 1 across the canvas, y up
+    pub position: [f32; 2],
+    // css pixels from the canvas's top left, and the size of the canvas
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    // 0 for a touch, a pen tip or the left button, 2 for the right button
+    pub button: i16,
+    pub shift: bool,
+    pub ctrl: bool,
+}
+
+impl Pointer {
+    // a pointer at a css pixel offset into a canvas of this size
+    pub fn at(id: i32, kind: PointerKind, offset: [f32; 2], size: [f32; 2]) -> Pointer {
+        let position = client_to_clip(
+            [offset[0] as f64, offset[1] as f64], [0.0, 0.0], [size[0] as f64, size[1] as f64]
+        );
+        Pointer { id, kind, position, offset, size, button: 0, shift: false, ctrl: false }
+    }
+}
+
+// what a demo is told about each pointer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerEvent {
+    pub phase: Phase,
+    pub pointer: Pointer,
+    // held down, for the move and up it is whether it was held down before
+    pub pressed: bool,
+    // moved far enough while pressed to be a drag rather than a tap
+    pub dragging: bool,
+    // the first of the pointers held down, or a mouse hovering with nothing held
+    pub primary: bool,
+    // how many pointers are held down, after this event
+    pub active: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Pressed {
+    id: i32,
+    start: [f32; 2],
+    dragging: bool,
+}
+
+// the pointers held down on one canvas, in the order they went down
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pointers {
+    pressed: Vec<Pressed>,
+}
+
+impl Pointers {
+    pub fn new() -> Pointers {
+        Pointers::default()
+    }
+
+    pub fn active(&self) -> usize {
+        self.pressed.len()
+    }
+
+    // forget every pointer held down, their ups went somewhere else
+    pub fn clear(&mut self) {
+        self.pressed.clear();
+    }
+
+    pub fn down(&mut self, pointer: Pointer) -> PointerEvent {
+        // a pointer can't go down twice, the browser lost an up somewhere
+        self.pressed.retain(|pressed| pressed.id != pointer.id);
+        self.pressed.push(Pressed { id: pointer.id, start: pointer.offset, dragging: false });
+        self.event(Phase::Down, pointer, true, false)
+    }
+
+    pub fn moved(&mut self, pointer: Pointer) -> PointerEvent {
+        let (pressed, dragging) = match self.pressed.iter_mut().find(|pressed| pressed.id == pointer.id) {
+            Some(pressed) => {
+                let (dx, dy) = (pointer.offset[0] - pressed.start[0], pointer.offset[1] - pressed.start[1]);
+                pressed.dragging |= dx.hypot(dy) >= DRAG_THRESHOLD;
+                (true, pressed.dragging)
+            }
+            None => (false, false),
+        };
+        self.event(Phase::Move, pointer, pressed, dragging)
+    }
+
+    // nothing to say about pointers that were never held down on this canvas
+    pub fn up(&mut self, pointer: Pointer) -> Option<PointerEvent> {
+        let index = self.pressed.iter().position(|pressed| pressed.id == pointer.id)?;
+        let primary = index == 0;
+        let released = self.pressed.remove(index);
+        Some(PointerEvent {
+            phase: Phase::Up,
+            pointer,
+            pressed: true,
+            dragging: released.dragging,
+            primary,
+            active: self.pressed.len(),
+        })
+    }
+
+    fn event(&self, phase: Phase, pointer: Pointer, pressed: bool, dragging: bool) -> PointerEvent {
+        let primary = match self.pressed.first() {
+            Some(first) => first.id == pointer.id,
+            None => pointer.kind == PointerKind::Mouse,
+        };
+        PointerEvent { phase, pointer, pressed, dragging, primary, active: self.pressed.len() }
+    }
+}
+
+fn read_pointer(element: &HtmlElement, event: &DomPointerEvent) -> Pointer {
+    let rect = element.get_bounding_client_rect();
+    let offset = [(event.client_x() as f64 - rect.x()) as f32, (event.client_y() as f64 - rect.y()) as f32];
+    let mut pointer = Pointer::at(
+        event.pointer_id(),
+        PointerKind::from_name(&event.pointer_type()),
+        offset,
+        [rect.width() as f32, rect.height() as f32],
+    );
+    pointer.button = event.button();
+    pointer.shift = event.shift_key();
+    pointer.ctrl = event.ctrl_key() || event.meta_key();
+    pointer
+}
+
+// send every mouse, touch and pen on a canvas to one callback, for as long as the
+// listeners are attached
+pub fn listen(
+    listeners: &mut Listeners,
+    canvas_id: &str,
+    on_pointer: impl FnMut(&PointerEvent) + 'static,
+) -> Result<(), ViewerError> {
+
+    let canvas: HtmlElement = get_element(canvas_id, "html element")?;
+
+    // let touches reach the demo rather than scrolling or zooming the page
+    canvas.style().set_property("touch-action", "none")
+        .map_err(|_| ViewerError::ContextUnavailable("canvas style".into()))?;
+
+    let pointers = Rc::new(RefCell::new(Pointers::new()));
+    let on_pointer = Rc::new(RefCell::new(on_pointer));
+
+    // a demo stopped part way through a touch never sees it lift, so don't keep
+    // it held down for when the demo starts again
+    let held = pointers.clone();
+    listeners.on_detach(move || held.borrow_mut().clear());
+
+    for (name, phase) in [
+        ("pointerdown", Phase::Down),
+        ("pointermove", Phase::Move),
+        ("pointerup", Phase::Up),
+        ("pointercancel", Phase::Up),
+    ] {
+        let element = canvas.clone();
+        let pointers = pointers.clone();
+        let on_pointer = on_pointer.clone();
+        listeners.listen(&canvas, name, move |event: DomPointerEvent| {
+            let pointer = read_pointer(&element, &event);
+            let event = match phase {
+                Phase::Down => {
+                    // keep getting this pointer's moves when it leaves the canvas
+                    let _ = element.set_pointer_capture(pointer.id);
+                    event.prevent_default();
+                    Some(pointers.borrow_mut().down(pointer))
+                }
+                Phase::Move => Some(pointers.borrow_mut().moved(pointer)),
+                Phase::Up => pointers.borrow_mut().up(pointer),
+            };
+            if let Some(event) = event {
+                (on_pointer.borrow_mut())(&event);
+            }
+        })?;
+    }
+
+    Ok(())
+}
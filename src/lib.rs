@@ -1,10 +1,27 @@
-mod triangle;
-mod square;
-mod special;
-mod gradient;
-mod point;
-mod sin_wave;
-mod shakes;
-mod drag;
+pub mod triangle;
+pub mod square;
+pub mod special;
+pub mod gradient;
+pub mod point;
+pub mod sin_wave;
+pub mod shakes;
+pub mod drag;
+pub mod lights;
 
-mod utils;
+pub mod animation;
+pub mod camera;
+pub mod clock;
+pub mod error;
+pub mod grid;
+pub mod history;
+pub mod input;
+pub mod listeners;
+pub mod math;
+pub mod physics;
+pub mod renderer;
+pub mod scene;
+pub mod seed;
+pub mod shadow;
+pub mod share;
+pub mod utils;
+pub mod viewport;
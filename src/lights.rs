@@ -0,0 +1,693 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlBuffer, WebGlProgram, WebGlRenderingContext};
+
+use crate::camera::{self, Camera, OrbitControls};
+use crate::animation::AnimationLoop;
+use crate::clock::{Clock, FixedStep};
+use crate::error::ViewerError;
+use crate::input::{self, Phase, PointerEvent};
+use crate::listeners::Listeners;
+use crate::math::{self, Mat4, Vec3};
+use crate::shadow::{self, ShadowMap};
+use crate::share::{self, Demo, Params};
+use crate::utils::{cached_program, init_webgl_context, pooled_buffer, static_buffer, upload_buffer};
+
+// number of spotlights the shader lights the scene with
+pub const LIGHT_COUNT: usize = 3;
+
+// the narrowest the fade at the edge of a beam gets, as a difference of cosines.
+// smoothstep needs its edges apart, and mediump floats only tell apart cosines
+// about this far apart near 1
+const MIN_PENUMBRA_COSINE: f32 = 1e-3;
+
+// the cube's width, height and depth
+const CUBE_SIZE: Vec3 = [5.0, 15.0, 7.0];
+
+// the grey patch of ground in the middle, which the cube can be dragged around
+const GROUND_SIZE: f32 = 100.0;
+
+// shadow bias slider steps in a unit of bias
+const BIAS_STEPS: f32 = 10000.0;
+
+// how far the targets sit from their lights when turned left and right
+const TARGET_RADIUS: f32 = 50.0;
+
+// position and normal per vertex, world space lighting per fragment
+const PHONG_VERTEX_SHADER_SOURCE: &str =
+    "
+    attribute vec3 position;
+    attribute vec3 normal;
+    uniform mat4 model;
+    uniform mat4 view_projection;
+    uniform mat3 normal_matrix;
+    varying vec3 world_position;
+    varying vec3 world_normal;
+
+    void main(void) {
+        vec4 world = model * vec4(position, 1.0);
+        world_position = world.xyz;
+        world_normal = normal_matrix * normal;
+        gl_Position = view_projection * world;
+    }
+    ";
+
+// the shader's arrays and shadow maps are written out for this many lights
+const _: () = assert!(LIGHT_COUNT == 3);
+
+// kept in step with `spot_factor`
+const PHONG_FRAGMENT_SHADER_SOURCE: &str =
+    "
+    #ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+    #else
+    precision mediump float;
+    #endif
+    uniform vec3 light_position[3];
+    uniform vec3 light_direction[3];
+    uniform vec3 light_colour[3];
+    uniform float light_intensity[3];
+    uniform float light_cone[3];
+    uniform float light_penumbra[3];
+    uniform vec3 camera_position;
+    uniform vec3 colour;
+    uniform float ambient;
+    uniform bool lit;
+    uniform mat4 light_matrix[3];
+    uniform sampler2D shadow_map_0;
+    uniform sampler2D shadow_map_1;
+    uniform sampler2D shadow_map_2;
+    uniform float shadow_bias;
+    uniform float shadow_texel;
+    varying vec3 world_position;
+    varying vec3 world_normal;
+
+    float unpack_depth(vec4 rgba) {
+        return dot(rgba, vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+    }
+
+    // kept in step with `shadow::pcf_visibility`
+    float visibility(sampler2D shadow_map, mat4 light) {
+        vec4 from_light = light * vec4(world_position, 1.0);
+        vec3 coord = from_light.xyz / from_light.w * 0.5 + 0.5;
+
+        // outside the light's view, so outside its beam too
+        if (from_light.w <= 0.0 || coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+            return 1.0;
+        }
+
+        float lit = 0.0;
+        for (int x = -1; x <= 1; x++) {
+            for (int y = -1; y <= 1; y++) {
+                float closest = unpack_depth(texture2D(shadow_map, coord.xy + vec2(x, y) * shadow_texel));
+                lit += coord.z - shadow_bias <= closest ? 1.0 : 0.0;
+            }
+        }
+        return lit / 9.0;
+    }
+
+    void main(void) {
+        if (!lit) {
+            gl_FragColor = vec4(colour, 1.0);
+            return;
+        }
+
+        // the planes are lit from both sides
+        vec3 n = normalize(world_normal);
+        if (!gl_FrontFacing) { n = -n; }
+        vec3 view = normalize(camera_position - world_position);
+
+        float shadows[3];
+        shadows[0] = visibility(shadow_map_0, light_matrix[0]);
+        shadows[1] = visibility(shadow_map_1, light_matrix[1]);
+        shadows[2] = visibility(shadow_map_2, light_matrix[2]);
+
+        vec3 result = ambient * colour;
+        for (int i = 0; i < 3; i++) {
+            vec3 to_light = normalize(light_position[i] - world_position);
+            float spot = smoothstep(light_cone[i], light_penumbra[i], dot(-to_light, light_direction[i])) * shadows[i];
+
+            float diffuse = max(dot(n, to_light), 0.0);
+            float specular = pow(max(dot(reflect(-to_light, n), view), 0.0), 30.0) * 0.3;
+            result += light_colour[i] * light_intensity[i] * spot * (diffuse * colour + specular);
+        }
+        gl_FragColor = vec4(result, 1.0);
+    }
+    ";
+
+// floats per vertex: x, y, z then the normal
+const MESH_STRIDE: usize = 6;
+
+// vertices in `cube_mesh` and `plane_mesh`, and lines per light in `guide_lines`
+const CUBE_VERTICES: i32 = 36;
+const PLANE_VERTICES: i32 = 6;
+const GUIDE_EDGES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Spotlight {
+    position: Vec3,
+    target: Vec3,
+    colour: [f32; 3],
+    intensity: f32,
+    // half the width of the beam, in radians
+    angle: f32,
+    // how much of the beam fades out towards its edge, 0 to 1
+    penumbra: f32,
+}
+
+impl Spotlight {
+    fn direction(&self) -> Vec3 {
+        math::normalize(math::sub(self.target, self.position))
+    }
+
+    // the left/right slider, the angle of the target around the light in degrees
+    fn turn(&self) -> f32 {
+        let turn = (self.position[0] - self.target[0]).atan2(self.position[2] - self.target[2]);
+        turn.to_degrees() + 180.0
+    }
+
+    // where the light sees the scene from, for its shadow map
+    fn view_projection(&self) -> Mat4 {
+        shadow::light_view_projection(self.position, self.direction(), self.angle)
+    }
+}
+
+// define the state
+#[allow(clippy::upper_case_acronyms)]
+struct STATE {
+    lights: Vec<Spotlight>,
+    ambient: f32,
+    cube_position: Vec3,
+    // where on the cube it was picked up, from its centre, while it's being dragged
+    cube_grab: Option<Vec3>,
+    guides: bool,
+    // whether the guide lines need uploading again, after a light has changed
+    guides_dirty: bool,
+    orbit: OrbitControls,
+    // the camera settles a tick at a time, whatever the frame rate
+    clock: Clock,
+    orbit_steps: FixedStep,
+    shadow_resolution: u32,
+    shadow_bias: f32,
+}
+
+// Initialize the state
+thread_local! {
+    static STATE: RefCell<STATE> = RefCell::new(STATE {
+        // three white lights in a row, each pointing at the ground below the next
+        lights: (0..LIGHT_COUNT).map(|i| {
+            let z = -50.0 + i as f32 * (100.0 / (LIGHT_COUNT - 1) as f32);
+            Spotlight {
+                position: [-50.0, 50.0, z],
+                target: [0.0, 0.0, z],
+                colour: [1.0, 1.0, 1.0],
+                intensity: 1.0,
+                angle: 0.1,
+                penumbra: 0.6,
+            }
+        }).collect(),
+        ambient: 0.5,
+        cube_position: [25.0, 5.0, 0.0],
+        cube_grab: None,
+        guides: true,
+        guides_dirty: true,
+        orbit: {
+            let camera = Camera::perspective(75.0_f32.to_radians(), 0.1, 1000.0).looking_at([75.0, 30.0, 0.0], [0.0; 3]);
+            let mut orbit = OrbitControls::new(camera);
+            // never look up from under the ground
+            orbit.max_polar_angle = PI / 2.0;
+            orbit
+        },
+        clock: Clock::new(),
+        orbit_steps: FixedStep::default(),
+        shadow_resolution: shadow::DEFAULT_SHADOW_RESOLUTION,
+        shadow_bias: shadow::DEFAULT_SHADOW_BIAS,
+    });
+}
+
+// the cosines of the edge of the cone and the edge of the penumbra inside it. a
+// beam with no width or no fade still gets a thin one, so the two never meet
+pub fn spot_cosines(angle: f32, penumbra: f32) -> (f32, f32) {
+    let inner = (angle * (1.0 - penumbra)).cos();
+    let cone = angle.cos().min(inner - MIN_PENUMBRA_COSINE);
+    (cone, inner)
+}
+
+// how much of a spotlight reaches a point, given the cosine of the angle between
+// the beam and the point. full inside the penumbra, none outside the cone
+pub fn spot_factor(cos_angle: f32, angle: f32, penumbra: f32) -> f32 {
+    let (cone, inner) = spot_cosines(angle, penumbra);
+    let t = ((cos_angle - cone) / (inner - cone)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// run a change against one light, ignoring indices that don't exist
+fn with_light(i: usize, f: impl FnOnce(&mut Spotlight)) {
+    let changed = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let changed = state.lights.get_mut(i).map(f).is_some();
+        state.guides_dirty |= changed;
+        changed
+    });
+    if changed {
+        share::update(Demo::Lights);
+    }
+}
+
+fn read_light<T: Default>(i: usize, f: impl FnOnce(&Spotlight) -> T) -> T {
+    STATE.with(|state| state.borrow().lights.get(i).map(f).unwrap_or_default())
+}
+
+#[wasm_bindgen]
+pub fn lights_count() -> usize {
+    LIGHT_COUNT
+}
+
+// turn a light left and right, in degrees around its position
+#[wasm_bindgen]
+pub fn lights_set_turn(i: usize, degrees: f32) {
+    with_light(i, |light| {
+        let radians = degrees * PI / 180.0;
+        light.target[0] = TARGET_RADIUS * radians.sin() + light.position[0];
+        light.target[2] = TARGET_RADIUS * radians.cos() + light.position[2];
+    });
+}
+
+// tilt a light up and down by moving the height of its target
+#[wasm_bindgen]
+pub fn lights_set_target_height(i: usize, y: f32) {
+    with_light(i, |light| light.target[1] = y);
+}
+
+#[wasm_bindgen]
+pub fn lights_set_intensity(i: usize, intensity: f32) {
+    with_light(i, |light| light.intensity = intensity.max(0.0));
+}
+
+// the size slider, half the width of the beam in radians
+#[wasm_bindgen]
+pub fn lights_set_size(i: usize, angle: f32) {
+    with_light(i, |light| light.angle = angle.clamp(0.0, PI / 2.0));
+}
+
+// the focus slider, how much of the beam fades out towards its edge
+#[wasm_bindgen]
+pub fn lights_set_focus(i: usize, penumbra: f32) {
+    with_light(i, |light| light.penumbra = penumbra.clamp(0.0, 1.0));
+}
+
+// set a light's colour from a css style hex string, like #ff8800
+#[wasm_bindgen]
+pub fn lights_set_colour(i: usize, hex: &str) {
+    if let Some(colour) = parse_hex(hex) {
+        with_light(i, |light| light.colour = colour);
+    }
+}
+
+// draw the edges of each beam
+#[wasm_bindgen]
+pub fn lights_set_guides(enabled: bool) {
+    STATE.with(|state| state.borrow_mut().guides = enabled);
+}
+
+// texels along each side of every light's shadow map, kept between 64 and 2048
+#[wasm_bindgen]
+pub fn lights_set_shadow_resolution(resolution: u32) {
+    STATE.with(|state| state.borrow_mut().shadow_resolution = shadow::clamp_resolution(resolution));
+    share::update(Demo::Lights);
+}
+
+// how far behind the nearest depth a point has to be before it's in shadow
+#[wasm_bindgen]
+pub fn lights_set_shadow_bias(bias: f32) {
+    if !bias.is_finite() { return; }
+    STATE.with(|state| state.borrow_mut().shadow_bias = bias.max(0.0));
+    share::update(Demo::Lights);
+}
+
+#[wasm_bindgen]
+pub fn lights_shadow_resolution() -> u32 {
+    STATE.with(|state| state.borrow().shadow_resolution)
+}
+
+#[wasm_bindgen]
+pub fn lights_shadow_bias() -> f32 {
+    STATE.with(|state| state.borrow().shadow_bias)
+}
+
+#[wasm_bindgen]
+pub fn lights_turn(i: usize) -> f32 {
+    read_light(i, Spotlight::turn)
+}
+
+#[wasm_bindgen]
+pub fn lights_target_height(i: usize) -> f32 {
+    read_light(i, |light| light.target[1])
+}
+
+#[wasm_bindgen]
+pub fn lights_intensity(i: usize) -> f32 {
+    read_light(i, |light| light.intensity)
+}
+
+#[wasm_bindgen]
+pub fn lights_size(i: usize) -> f32 {
+    read_light(i, |light| light.angle)
+}
+
+#[wasm_bindgen]
+pub fn lights_focus(i: usize) -> f32 {
+    read_light(i, |light| light.penumbra)
+}
+
+#[wasm_bindgen]
+pub fn lights_colour(i: usize) -> String {
+    read_light(i, |light| to_hex(light.colour))
+}
+
+fn parse_hex(hex: &str) -> Option<[f32; 3]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 { return None; }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([16, 8, 0].map(|shift| ((value >> shift) & 0xff) as f32 / 255.0))
+}
+
+fn to_hex(colour: [f32; 3]) -> String {
+    colour.iter().map(|c| format!("{:02x}", (c.clamp(0.0, 1.0) * 255.0).round() as u8)).collect()
+}
+
+// every light's settings, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    let mut params = Params::new();
+    for i in 0..LIGHT_COUNT {
+        params.set(&format!("l{}_c", i), lights_colour(i));
+        params.set(&format!("l{}_i", i), share::number(lights_intensity(i)));
+        params.set(&format!("l{}_a", i), share::number(lights_size(i)));
+        params.set(&format!("l{}_p", i), share::number(lights_focus(i)));
+        params.set(&format!("l{}_ty", i), share::number(lights_target_height(i)));
+        params.set(&format!("l{}_lr", i), share::number(lights_turn(i)));
+    }
+    params.set("shadow_res", lights_shadow_resolution().to_string());
+    // in the bias slider's steps, which are too fine for a number to keep
+    params.set("shadow_bias", share::number(lights_shadow_bias() * BIAS_STEPS));
+    params
+}
+
+pub(crate) fn load_params(params: &Params) {
+    let finite = |key: String| params.number::<f32>(&key).filter(|v| v.is_finite());
+    for i in 0..LIGHT_COUNT {
+        if let Some(hex) = params.get(&format!("l{}_c", i)) { lights_set_colour(i, hex); }
+        if let Some(v) = finite(format!("l{}_i", i)) { lights_set_intensity(i, v); }
+        if let Some(v) = finite(format!("l{}_a", i)) { lights_set_size(i, v); }
+        if let Some(v) = finite(format!("l{}_p", i)) { lights_set_focus(i, v); }
+        if let Some(v) = finite(format!("l{}_ty", i)) { lights_set_target_height(i, v); }
+        if let Some(v) = finite(format!("l{}_lr", i)) { lights_set_turn(i, v); }
+    }
+    if let Some(resolution) = params.number("shadow_res") { lights_set_shadow_resolution(resolution); }
+    if let Some(bias) = finite("shadow_bias".into()) { lights_set_shadow_bias(bias / BIAS_STEPS); }
+}
+
+#[wasm_bindgen]
+pub fn lights_cube_position() -> Vec<f32> {
+    STATE.with(|state| state.borrow().cube_position.to_vec())
+}
+
+// pick the cube up with the pointer and slide it across the ground, holding the
+// camera still while it moves
+pub fn lights_pointer(event: &PointerEvent) {
+    if !event.primary { return; }
+    let pointer = &event.pointer;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let aspect = pointer.size[0] / pointer.size[1].max(1.0);
+        let (origin, direction) = state.orbit.camera().ray(pointer.position, aspect);
+
+        match event.phase {
+            Phase::Down => {
+                // a drag that never saw its pointer go up, the demo was stopped part way
+                if state.cube_grab.take().is_some() {
+                    state.orbit.enabled = true;
+                }
+
+                // the other buttons, and shift or ctrl, pan the camera
+                if pointer.button != 0 || pointer.shift || pointer.ctrl { return; }
+                let half = math::scale(CUBE_SIZE, 0.5);
+                let (min, max) = (math::sub(state.cube_position, half), math::add(state.cube_position, half));
+                let Some(t) = math::ray_box(origin, direction, min, max) else { return; };
+
+                let hit = math::add(origin, math::scale(direction, t));
+                state.cube_grab = Some(math::sub(hit, state.cube_position));
+                state.orbit.enabled = false;
+            }
+            Phase::Move if event.pressed => {
+                let Some(grab) = state.cube_grab else { return; };
+
+                // keep the point it was picked up by under the pointer, at the same height
+                let height = state.cube_position[1] + grab[1];
+                let Some(point) = math::ray_plane(origin, direction, height) else { return; };
+                let limit = (GROUND_SIZE - CUBE_SIZE[0].max(CUBE_SIZE[2])) / 2.0;
+                state.cube_position[0] = (point[0] - grab[0]).clamp(-limit, limit);
+                state.cube_position[2] = (point[2] - grab[2]).clamp(-limit, limit);
+            }
+            // a mouse hovering after a drag whose up was never seen lets go too
+            Phase::Move | Phase::Up => {
+                if state.cube_grab.take().is_some() {
+                    state.orbit.enabled = true;
+                }
+            }
+        }
+    });
+}
+
+// a unit cube centred on the origin, with a normal per face
+fn cube_mesh() -> Vec<f32> {
+    let faces: [(Vec3, Vec3, Vec3); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+    ];
+    faces.iter().flat_map(|(normal, u, v)| {
+        let center = math::scale(*normal, 0.5);
+        let corner = move |a: f32, b: f32| math::add(center, math::add(math::scale(*u, a), math::scale(*v, b)));
+        [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (-0.5, 0.5), (0.5, -0.5), (0.5, 0.5)]
+            .into_iter()
+            .flat_map(move |(a, b)| corner(a, b).into_iter().chain(*normal))
+    }).collect()
+}
+
+// a unit square on the ground, facing up
+fn plane_mesh() -> Vec<f32> {
+    [(-0.5, -0.5), (-0.5, 0.5), (0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)]
+        .into_iter()
+        .flat_map(|(x, z)| [x, 0.0, z, 0.0, 1.0, 0.0])
+        .collect()
+}
+
+// lines from each light out along the edges of its beam
+fn guide_lines(lights: &[Spotlight]) -> Vec<f32> {
+    const LENGTH: f32 = 1000.0;
+    lights.iter().flat_map(|light| {
+        let facing = math::facing(light.direction());
+        let radius = LENGTH * light.angle.tan();
+        (0..GUIDE_EDGES).flat_map(move |k| {
+            let theta = 2.0 * PI * k as f32 / GUIDE_EDGES as f32;
+            let end = math::transform_point(&facing, [radius * theta.cos(), radius * theta.sin(), LENGTH]);
+            let end = math::add(light.position, end);
+            light.position.into_iter().chain([0.0; 3]).chain(end).chain([0.0; 3])
+        })
+    }).collect()
+}
+
+// start the demo on a canvas, the handle stops and restarts it
+#[wasm_bindgen]
+pub fn lights_init(canvas_id: &str) -> Result<AnimationLoop, ViewerError> {
+
+    // pick up a shared lighting setup
+    share::restore(Demo::Lights);
+
+    // drag the cube around, or anywhere else to look around the scene. the cube
+    // listens first, so the camera sees it's been turned off before it would move
+    let mut listeners = Listeners::new();
+    input::listen(&mut listeners, canvas_id, lights_pointer)?;
+    camera::attach_orbit_controls(&mut listeners, canvas_id, with_orbit)?;
+
+    let gl = init_webgl_context(canvas_id)?;
+    gl.enable(WebGlRenderingContext::DEPTH_TEST);
+
+    // a fresh context has no guide lines in it yet
+    STATE.with(|state| state.borrow_mut().guides_dirty = true);
+
+    // start animation loop
+    AnimationLoop::for_canvas(canvas_id, listeners, move || frame(&gl))
+}
+
+fn with_orbit(f: &mut dyn FnMut(&mut OrbitControls)) {
+    STATE.with(|state| f(&mut state.borrow_mut().orbit));
+}
+
+fn frame(gl: &WebGlRenderingContext) -> Result<(), ViewerError> {
+
+    // let the camera drift to a stop. it follows the user, so it keeps going while paused
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.clock.tick();
+        let elapsed = state.clock.real_delta();
+        let steps = state.orbit_steps.advance(elapsed);
+        for _ in 0..steps {
+            state.orbit.update();
+        }
+    });
+
+    render(gl)
+}
+
+// point the position and normal attributes at a mesh already in a buffer
+fn bind_mesh(gl: &WebGlRenderingContext, program: &WebGlProgram, buffer: &WebGlBuffer) {
+    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+
+    let float_size = std::mem::size_of::<f32>() as i32;
+    let stride = MESH_STRIDE as i32 * float_size;
+    for (attribute, start) in [("position", 0), ("normal", 3)] {
+        // the depth pass has no normals
+        let location = gl.get_attrib_location(program, attribute);
+        if location < 0 { continue; }
+        let location = location as u32;
+        gl.vertex_attrib_pointer_with_i32(location, 3, WebGlRenderingContext::FLOAT, false, stride, start * float_size);
+        gl.enable_vertex_attrib_array(location);
+    }
+}
+
+// set the per object uniforms and draw the bound mesh
+fn draw_object(
+    gl: &WebGlRenderingContext,
+    program: &WebGlProgram,
+    mode: u32,
+    count: i32,
+    model: &Mat4,
+    colour: [f32; 3],
+    lit: bool,
+) {
+    let uniform = |name: &str| gl.get_uniform_location(program, name);
+    gl.uniform_matrix4fv_with_f32_array(uniform("model").as_ref(), false, model);
+    gl.uniform_matrix3fv_with_f32_array(uniform("normal_matrix").as_ref(), false, &math::normal_matrix(model));
+    gl.uniform3fv_with_f32_array(uniform("colour").as_ref(), &colour);
+    gl.uniform1i(uniform("lit").as_ref(), lit as i32);
+    gl.draw_arrays(mode, 0, count);
+}
+
+fn cube_model(state: &STATE) -> Mat4 {
+    math::multiply(&math::translation(state.cube_position), &math::scaling(CUBE_SIZE))
+}
+
+// render what each light can see of the cube into its shadow map
+fn render_shadow_maps(gl: &WebGlRenderingContext, state: &STATE, maps: &[ShadowMap]) -> Result<(), ViewerError> {
+
+    let program = cached_program(gl, shadow::DEPTH_VERTEX_SHADER_SOURCE, shadow::DEPTH_FRAGMENT_SHADER_SOURCE)?;
+    bind_mesh(gl, &program, &static_buffer(gl, "lights_cube", cube_mesh)?);
+    let light_view_projection = gl.get_uniform_location(&program, "light_view_projection");
+
+    for (light, map) in state.lights.iter().zip(maps) {
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&map.framebuffer));
+        gl.viewport(0, 0, map.resolution as i32, map.resolution as i32);
+
+        // nothing in the way is as far as a depth can be
+        gl.clear_color(1.0, 1.0, 1.0, 1.0);
+        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
+
+        gl.uniform_matrix4fv_with_f32_array(light_view_projection.as_ref(), false, &light.view_projection());
+        draw_object(gl, &program, WebGlRenderingContext::TRIANGLES, CUBE_VERTICES, &cube_model(state), [0.0; 3], false);
+    }
+
+    gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+    Ok(())
+}
+
+// draw the ground, the cube and the lights from the camera
+fn render(gl: &WebGlRenderingContext) -> Result<(), ViewerError> {
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+
+        let maps = shadow::shadow_maps(gl, LIGHT_COUNT, state.shadow_resolution)?;
+        render_shadow_maps(gl, &state, &maps)?;
+
+        let program = cached_program(gl, PHONG_VERTEX_SHADER_SOURCE, PHONG_FRAGMENT_SHADER_SOURCE)?;
+        let (width, height) = (gl.drawing_buffer_width(), gl.drawing_buffer_height());
+        gl.viewport(0, 0, width, height);
+
+        // the camera and the lights are the same for every object
+        let aspect = width as f32 / height.max(1) as f32;
+        let camera = state.orbit.camera();
+
+        let uniform = |name: &str| gl.get_uniform_location(&program, name);
+        gl.uniform_matrix4fv_with_f32_array(uniform("view_projection").as_ref(), false, &camera.view_projection(aspect));
+        gl.uniform3fv_with_f32_array(uniform("camera_position").as_ref(), &camera.eye);
+        gl.uniform1f(uniform("ambient").as_ref(), state.ambient);
+
+        let per_light = |f: &dyn Fn(&Spotlight) -> Vec<f32>| -> Vec<f32> {
+            state.lights.iter().flat_map(f).collect()
+        };
+        gl.uniform3fv_with_f32_array(uniform("light_position").as_ref(), &per_light(&|l| l.position.to_vec()));
+        gl.uniform3fv_with_f32_array(uniform("light_direction").as_ref(), &per_light(&|l| l.direction().to_vec()));
+        gl.uniform3fv_with_f32_array(uniform("light_colour").as_ref(), &per_light(&|l| l.colour.to_vec()));
+        gl.uniform1fv_with_f32_array(uniform("light_intensity").as_ref(), &per_light(&|l| vec![l.intensity]));
+        gl.uniform1fv_with_f32_array(uniform("light_cone").as_ref(), &per_light(&|l| vec![spot_cosines(l.angle, l.penumbra).0]));
+        gl.uniform1fv_with_f32_array(uniform("light_penumbra").as_ref(), &per_light(&|l| vec![spot_cosines(l.angle, l.penumbra).1]));
+
+        // each light's shadow map on its own texture unit
+        gl.uniform_matrix4fv_with_f32_array(
+            uniform("light_matrix").as_ref(), false, &per_light(&|l| l.view_projection().to_vec())
+        );
+        for (i, map) in maps.iter().enumerate() {
+            gl.active_texture(WebGlRenderingContext::TEXTURE0 + i as u32);
+            gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&map.texture));
+            gl.uniform1i(uniform(&format!("shadow_map_{}", i)).as_ref(), i as i32);
+        }
+        gl.uniform1f(uniform("shadow_bias").as_ref(), state.shadow_bias);
+        let resolution = maps.first().map_or(state.shadow_resolution, |map| map.resolution);
+        gl.uniform1f(uniform("shadow_texel").as_ref(), 1.0 / resolution as f32);
+
+        // draw on the screen
+        gl.clear_color(0.06, 0.02, 0.47, 1.0);
+        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
+
+        // the floor stretches off into the distance, the ground is a patch in the middle
+        bind_mesh(gl, &program, &static_buffer(gl, "lights_plane", plane_mesh)?);
+        let floor = math::multiply(&math::translation([0.0, -0.1, 0.0]), &math::scaling([10000.0, 1.0, 10000.0]));
+        draw_object(gl, &program, WebGlRenderingContext::TRIANGLES, PLANE_VERTICES, &floor, [0.5, 0.94, 0.5], true);
+        let ground = math::scaling([GROUND_SIZE, 1.0, GROUND_SIZE]);
+        draw_object(gl, &program, WebGlRenderingContext::TRIANGLES, PLANE_VERTICES, &ground, [0.5, 0.5, 0.5], true);
+
+        bind_mesh(gl, &program, &static_buffer(gl, "lights_cube", cube_mesh)?);
+        draw_object(gl, &program, WebGlRenderingContext::TRIANGLES, CUBE_VERTICES, &cube_model(&state), [0.94, 0.06, 0.94], true);
+
+        // a box for each light, pointing where it shines, in its own colour
+        for light in state.lights.iter() {
+            let model = math::multiply(
+                &math::multiply(&math::translation(light.position), &math::facing(light.direction())),
+                &math::scaling([5.0, 5.0, 15.0]),
+            );
+            draw_object(gl, &program, WebGlRenderingContext::TRIANGLES, CUBE_VERTICES, &model, light.colour, false);
+        }
+
+        if state.guides {
+            // only uploaded again when a light has moved or changed its beam
+            let buffer = pooled_buffer(gl, "lights_guides")?;
+            if state.guides_dirty {
+                upload_buffer(gl, &buffer, &guide_lines(&state.lights));
+                state.guides_dirty = false;
+            }
+            bind_mesh(gl, &program, &buffer);
+            let count = (state.lights.len() * GUIDE_EDGES * 2) as i32;
+            draw_object(gl, &program, WebGlRenderingContext::LINES, count, &math::IDENTITY, [1.0, 1.0, 1.0], false);
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,104 @@
+use std::any::Any;
+use std::fmt;
+
+use js_sys::Function;
+use wasm_bindgen::convert::FromWasmAbi;
+use wasm_bindgen::prelude::*;
+use web_sys::EventTarget;
+
+use crate::error::ViewerError;
+
+struct Listener {
+    target: EventTarget,
+    event: String,
+    function: Function,
+    // owns the rust side of `function`, which only works while this is alive
+    _closure: Box<dyn Any>,
+}
+
+// the event listeners a demo has set up, so they can all be taken off the page
+// when it stops and put back when it starts again. dropping them takes them off
+#[derive(Default)]
+pub struct Listeners {
+    listeners: Vec<Listener>,
+    // run on every detach, to forget anything only the missed events would have undone
+    on_detach: Vec<Box<dyn FnMut()>>,
+    attached: bool,
+}
+
+impl Listeners {
+    pub fn new() -> Listeners {
+        Listeners::default()
+    }
+
+    pub fn attached(&self) -> bool {
+        self.attached
+    }
+
+    // call `handler` with every `event` on `target`, from now on if these are
+    // attached or from the next `attach` if not
+    pub fn listen<E: FromWasmAbi + 'static>(
+        &mut self,
+        target: &EventTarget,
+        event: &str,
+        handler: impl FnMut(E) + 'static,
+    ) -> Result<(), ViewerError> {
+        let closure: Closure<dyn FnMut(E)> = Closure::wrap(Box::new(handler));
+        let listener = Listener {
+            target: target.clone(),
+            event: event.to_owned(),
+            function: closure.as_ref().unchecked_ref::<Function>().clone(),
+            _closure: Box::new(closure),
+        };
+        if self.attached {
+            add(&listener)?;
+        }
+        self.listeners.push(listener);
+        Ok(())
+    }
+
+    // call `reset` whenever these are taken off the page
+    pub fn on_detach(&mut self, reset: impl FnMut() + 'static) {
+        self.on_detach.push(Box::new(reset));
+    }
+
+    pub fn attach(&mut self) -> Result<(), ViewerError> {
+        if self.attached { return Ok(()); }
+        for listener in self.listeners.iter() {
+            add(listener)?;
+        }
+        self.attached = true;
+        Ok(())
+    }
+
+    pub fn detach(&mut self) {
+        if !self.attached { return; }
+        for listener in self.listeners.iter() {
+            let _ = listener.target.remove_event_listener_with_callback(&listener.event, &listener.function);
+        }
+        for reset in self.on_detach.iter_mut() {
+            reset();
+        }
+        self.attached = false;
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Listeners")
+            .field("events", &self.listeners.iter().map(|l| l.event.as_str()).collect::<Vec<_>>())
+            .field("attached", &self.attached)
+            .finish()
+    }
+}
+
+impl Drop for Listeners {
+    fn drop(&mut self) {
+        self.detach();
+    }
+}
+
+fn add(listener: &Listener) -> Result<(), ViewerError> {
+    listener.target.add_event_listener_with_callback(&listener.event, &listener.function)
+        .map_err(|_| ViewerError::Listener(listener.event.clone()))
+}
@@ -0,0 +1,169 @@
+// just enough 3d maths for the lights demo and its camera. matrices are column
+// major, the way webgl expects them in uniform_matrix4fv
+
+pub type Vec3 = [f32; 3];
+pub type Mat4 = [f32; 16];
+
+pub const IDENTITY: Mat4 = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+pub fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: Vec3, s: f32) -> Vec3 {
+    a.map(|v| v * s)
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn normalize(a: Vec3) -> Vec3 {
+    let l = length(a);
+    if l > 0.0 { scale(a, 1.0 / l) } else { a }
+}
+
+// a * b, so b is applied first
+pub fn multiply(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+// a point through a matrix, including the perspective divide
+pub fn transform_point(m: &Mat4, p: Vec3) -> Vec3 {
+    let v = [p[0], p[1], p[2], 1.0];
+    let out: [f32; 4] = std::array::from_fn(|row| (0..4).map(|k| m[k * 4 + row] * v[k]).sum());
+    [out[0] / out[3], out[1] / out[3], out[2] / out[3]]
+}
+
+pub fn translation(t: Vec3) -> Mat4 {
+    let mut m = IDENTITY;
+    m[12..15].copy_from_slice(&t);
+    m
+}
+
+pub fn scaling(s: Vec3) -> Mat4 {
+    let mut m = IDENTITY;
+    m[0] = s[0];
+    m[5] = s[1];
+    m[10] = s[2];
+    m
+}
+
+// a rotation taking the x, y and z axes onto these
+pub fn basis(x: Vec3, y: Vec3, z: Vec3) -> Mat4 {
+    [
+        x[0], x[1], x[2], 0.0,
+        y[0], y[1], y[2], 0.0,
+        z[0], z[1], z[2], 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ]
+}
+
+// a rotation pointing the z axis along a direction, keeping y as close to up as it can
+pub fn facing(direction: Vec3) -> Mat4 {
+    let z = normalize(direction);
+    let up = if cross([0.0, 1.0, 0.0], z) == [0.0; 3] { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let x = normalize(cross(up, z));
+    basis(x, cross(z, x), z)
+}
+
+// opengl style projection, depth runs from -1 at near to 1 at far
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let depth = 1.0 / (near - far);
+    [
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, f, 0.0, 0.0,
+        0.0, 0.0, (far + near) * depth, -1.0,
+        0.0, 0.0, 2.0 * far * near * depth, 0.0,
+    ]
+}
+
+// a box of the world squashed into clip space, depth from -1 at near to 1 at far
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    [
+        2.0 / (right - left), 0.0, 0.0, 0.0,
+        0.0, 2.0 / (top - bottom), 0.0, 0.0,
+        0.0, 0.0, 2.0 / (near - far), 0.0,
+        (left + right) / (left - right), (bottom + top) / (bottom - top), (near + far) / (near - far), 1.0,
+    ]
+}
+
+// move the world so the eye sits at the origin looking down -z
+pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    let z = normalize(sub(eye, target));
+    let x = normalize(cross(up, z));
+    let y = cross(z, x);
+    [
+        x[0], y[0], z[0], 0.0,
+        x[1], y[1], z[1], 0.0,
+        x[2], y[2], z[2], 0.0,
+        -dot(x, eye), -dot(y, eye), -dot(z, eye), 1.0,
+    ]
+}
+
+// how far along a ray it first meets an axis aligned box, if it does at all
+pub fn ray_box(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<f32> {
+    let (mut near, mut far) = (0.0_f32, f32::INFINITY);
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] { return None; }
+            continue;
+        }
+        let t0 = (min[axis] - origin[axis]) / direction[axis];
+        let t1 = (max[axis] - origin[axis]) / direction[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    (near <= far).then_some(near)
+}
+
+// where a ray crosses the flat plane at height y, if it heads towards it
+pub fn ray_plane(origin: Vec3, direction: Vec3, y: f32) -> Option<Vec3> {
+    if direction[1] == 0.0 { return None; }
+    let t = (y - origin[1]) / direction[1];
+    (t >= 0.0).then(|| add(origin, scale(direction, t)))
+}
+
+// the inverse transpose of the top left 3x3, for carrying normals through a model matrix
+pub fn normal_matrix(m: &Mat4) -> [f32; 9] {
+    let a = |col: usize, row: usize| m[col * 4 + row];
+    let cofactor = |c0: usize, c1: usize, r0: usize, r1: usize| a(c0, r0) * a(c1, r1) - a(c1, r0) * a(c0, r1);
+
+    // the cofactor matrix, column major, is the inverse transpose scaled by the determinant
+    let c = [
+        cofactor(1, 2, 1, 2), -cofactor(1, 2, 0, 2), cofactor(1, 2, 0, 1),
+        -cofactor(0, 2, 1, 2), cofactor(0, 2, 0, 2), -cofactor(0, 2, 0, 1),
+        cofactor(0, 1, 1, 2), -cofactor(0, 1, 0, 2), cofactor(0, 1, 0, 1),
+    ];
+    let det = a(0, 0) * c[0] + a(0, 1) * c[1] + a(0, 2) * c[2];
+    if det == 0.0 { return c; }
+    c.map(|v| v / det)
+}
@@ -0,0 +1,144 @@
+use euclid::{self, Box2D, Vector2D};
+use rand::Rng;
+
+// the simulation always moves on by this much, however often frames come
+pub const FIXED_STEP: f64 = 1.0 / 120.0;
+
+// clip space units per second per second, down
+pub const GRAVITY: f64 = 3.0;
+
+// how much of its speed a box keeps after hitting something
+pub const RESTITUTION: f64 = 0.5;
+
+// bounces slower than this stop dead, so resting boxes don't buzz
+const REST_SPEED: f64 = 0.05;
+
+// a rect that moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub rect: Box2D<f64, f64>,
+    pub velocity: Vector2D<f64, f64>,
+}
+
+impl Body {
+    pub fn at_rest(rect: Box2D<f64, f64>) -> Body {
+        Body { rect, velocity: euclid::vec2(0.0, 0.0) }
+    }
+
+    // rects can be stored with their corners either way round
+    fn normalize(&mut self) {
+        let (min, max) = (self.rect.min.min(self.rect.max), self.rect.min.max(self.rect.max));
+        self.rect = Box2D::new(min, max);
+    }
+
+    // bigger boxes are harder to push around
+    fn inverse_mass(&self) -> f64 {
+        let area = self.rect.width() * self.rect.height();
+        if area > 0.0 { 1.0 / area } else { 0.0 }
+    }
+}
+
+// what the boxes fall through and bounce around in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct World {
+    pub gravity: f64,
+    pub restitution: f64,
+    pub bounds: Box2D<f64, f64>,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World {
+            gravity: GRAVITY,
+            restitution: RESTITUTION,
+            bounds: Box2D::new(euclid::point2(-1.0, -1.0), euclid::point2(1.0, 1.0)),
+        }
+    }
+}
+
+impl World {
+    // move every body on by dt seconds, then push apart anything that ended up overlapping
+    pub fn step(&self, bodies: &mut [Body], dt: f64) {
+        for body in bodies.iter_mut() {
+            body.normalize();
+            body.velocity.y -= self.gravity * dt;
+            body.rect = body.rect.translate(body.velocity * dt);
+            self.keep_inside(body);
+        }
+
+        for i in 0..bodies.len() {
+            for j in i + 1..bodies.len() {
+                let (before, after) = bodies.split_at_mut(j);
+                self.collide(&mut before[i], &mut after[0]);
+            }
+        }
+
+        // pushing boxes apart can push one through a wall
+        for body in bodies.iter_mut() {
+            self.keep_inside(body);
+        }
+    }
+
+    // bounce off the walls of the canvas
+    fn keep_inside(&self, body: &mut Body) {
+        let bounce = |speed: f64| {
+            let speed = -speed * self.restitution;
+            if speed.abs() < REST_SPEED { 0.0 } else { speed }
+        };
+
+        if body.rect.min.x < self.bounds.min.x {
+            body.rect = body.rect.translate(euclid::vec2(self.bounds.min.x - body.rect.min.x, 0.0));
+            if body.velocity.x < 0.0 { body.velocity.x = bounce(body.velocity.x); }
+        } else if body.rect.max.x > self.bounds.max.x {
+            body.rect = body.rect.translate(euclid::vec2(self.bounds.max.x - body.rect.max.x, 0.0));
+            if body.velocity.x > 0.0 { body.velocity.x = bounce(body.velocity.x); }
+        }
+
+        if body.rect.min.y < self.bounds.min.y {
+            body.rect = body.rect.translate(euclid::vec2(0.0, self.bounds.min.y - body.rect.min.y));
+            if body.velocity.y < 0.0 { body.velocity.y = bounce(body.velocity.y); }
+        } else if body.rect.max.y > self.bounds.max.y {
+            body.rect = body.rect.translate(euclid::vec2(0.0, self.bounds.max.y - body.rect.max.y));
+            if body.velocity.y > 0.0 { body.velocity.y = bounce(body.velocity.y); }
+        }
+    }
+
+    // push two overlapping boxes apart along whichever way they overlap least,
+    // and swap momentum between them if they're still closing
+    fn collide(&self, a: &mut Body, b: &mut Body) {
+        let overlap_x = a.rect.max.x.min(b.rect.max.x) - a.rect.min.x.max(b.rect.min.x);
+        let overlap_y = a.rect.max.y.min(b.rect.max.y) - a.rect.min.y.max(b.rect.min.y);
+        if overlap_x <= 0.0 || overlap_y <= 0.0 { return; }
+
+        // the way from a to b, along one axis
+        let (normal, depth): (Vector2D<f64, f64>, f64) = if overlap_x < overlap_y {
+            let sign = if b.rect.center().x >= a.rect.center().x { 1.0 } else { -1.0 };
+            (euclid::vec2(sign, 0.0), overlap_x)
+        } else {
+            let sign = if b.rect.center().y >= a.rect.center().y { 1.0 } else { -1.0 };
+            (euclid::vec2(0.0, sign), overlap_y)
+        };
+
+        let (inverse_a, inverse_b) = (a.inverse_mass(), b.inverse_mass());
+        let total = inverse_a + inverse_b;
+        if total <= 0.0 { return; }
+
+        // the lighter box moves further
+        a.rect = a.rect.translate(normal * (-depth * inverse_a / total));
+        b.rect = b.rect.translate(normal * (depth * inverse_b / total));
+
+        let closing = (b.velocity - a.velocity).dot(normal);
+        if closing >= 0.0 { return; }
+
+        let impulse = -(1.0 + self.restitution) * closing / total;
+        a.velocity -= normal * (impulse * inverse_a);
+        b.velocity += normal * (impulse * inverse_b);
+    }
+}
+
+// a random kick of up to `strength` each way, scaled to a step so it adds up the
+// same whatever the frame rate
+pub fn shake(rng: &mut impl Rng, strength: f64, dt: f64) -> Vector2D<f64, f64> {
+    if strength <= 0.0 { return euclid::vec2(0.0, 0.0); }
+    euclid::vec2(rng.gen_range(-strength..strength), rng.gen_range(-strength..strength)) * dt
+}
@@ -1,94 +1,119 @@
 use wasm_bindgen::prelude::*;
 use web_sys::{WebGlProgram, WebGlRenderingContext};
 use palette::Srgb;
-use std::{cell::RefCell, collections::HashMap};
-
-use rand::Rng;
-
-extern crate js_sys;
-
-use crate::utils::{init_webgl_context, link_shaders};
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::animation::AnimationLoop;
+use crate::error::ViewerError;
+use crate::grid::GridMesh;
+use crate::listeners::Listeners;
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::seed::current_seed;
+use crate::share::{self, Demo, Params};
+use crate::utils::{cached_program, init_webgl_context, pooled_buffer, Instancing};
+
+// one cell of the grid, drawn once per instance. each corner carries its
+// position within the cell and which of the instance's six colours it takes
+const INSTANCED_VERTEX_SHADER_SOURCE: &str =
+    "
+    attribute vec3 corner;
+    attribute vec2 offset;
+    attribute vec3 colour0;
+    attribute vec3 colour1;
+    attribute vec3 colour2;
+    attribute vec3 colour3;
+    attribute vec3 colour4;
+    attribute vec3 colour5;
+    uniform float cell_size;
+    varying vec3 out_colour;
+
+    void main(void) {
+        gl_Position = vec4(offset + corner.xy * cell_size, 0.0, 1.0);
+
+        out_colour = colour0;
+        if (corner.z > 0.5) { out_colour = colour1; }
+        if (corner.z > 1.5) { out_colour = colour2; }
+        if (corner.z > 2.5) { out_colour = colour3; }
+        if (corner.z > 3.5) { out_colour = colour4; }
+        if (corner.z > 4.5) { out_colour = colour5; }
+    }
+    ";
+const INSTANCED_FRAGMENT_SHADER_SOURCE: &str =
+    "
+    precision mediump float;
+    varying vec3 out_colour;
+
+    void main(void) {
+        gl_FragColor = vec4(out_colour, 1.0);
+    }
+    ";
+
+// x, y and colour index of the six corners, in the same order as make_square
+const UNIT_SQUARE: [f32; 18] = [
+    0.0, 0.0, 0.0,
+    1.0, 0.0, 1.0,
+    0.0, 1.0, 2.0,
+    0.0, 1.0, 3.0,
+    1.0, 0.0, 4.0,
+    1.0, 1.0, 5.0,
+];
+
+// floats per instance: the cell offset then one rgb colour per corner
+const INSTANCE_STRIDE: usize = 2 + 6 * 3;
+
+// the resolutions the slider offers, a link asking for anything else
+// is ignored, so one can't ask for a grid too big to build
+const MIN_RESOLUTION: i32 = 1;
+const MAX_RESOLUTION: i32 = 60;
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 struct STATE {
-    resolution: i32,
-    pixels: Vec<f32>,
+    grid: GridMesh,
     colours: Vec<Srgb>,
     pointwise: bool,
+    rng: StdRng,
+    instances_dirty: bool,
 }
 
+impl STATE {
+    // pick new colours for the current grid
+    fn recolour(&mut self) {
+        let count = if self.pointwise { self.grid.corner_count() } else { self.grid.indices().len() };
+        self.colours = random_colours(count, &mut self.rng);
+        self.instances_dirty = true;
+    }
 
-// Initialize the state
-thread_local! {
-    static STATE: RefCell<STATE> = RefCell::new(STATE {
-        resolution: 1,
-        pointwise: false,
-        pixels: make_pixels(1),
-        colours: make_colours(make_pixels(1), false),
-    });
+    // the colour of one vertex of a cell. pointwise colours belong to the
+    // shared corner, individual ones to the vertex itself
+    fn vertex_colour(&self, cell: usize, vertex: usize) -> Srgb {
+        let i = cell * 6 + vertex;
+        if self.pointwise { self.colours[self.grid.indices()[i] as usize] } else { self.colours[i] }
+    }
 }
 
 
-// return two triangles
-fn make_square(res: i32, x: i32, y: i32) -> Vec<f32> {
-
-    let triangles = vec![
-        x, y,
-        x + 1, y,
-        x, y + 1,
-        x, y + 1,
-        x + 1, y,
-        x + 1, y + 1,
-    ];
-
-    // map triangles onto range -1 to 1
-    triangles.iter().map(|v| (*v as f32 / res as f32) * 2.0 - 1.0).collect::<Vec<f32>>()
-}
-
-fn make_pixels(resolution: i32) -> Vec<f32> {
-    (0..resolution*2)
-        .flat_map(|x| (0..resolution*2)
-        .flat_map(move |y| make_square(resolution, x, y)))
-        .collect()
+// Initialize the state
+thread_local! {
+    static STATE: RefCell<STATE> = RefCell::new({
+        let mut rng = StdRng::seed_from_u64(current_seed());
+        STATE {
+            pointwise: false,
+            colours: random_colours(GridMesh::new(1).indices().len(), &mut rng),
+            grid: GridMesh::new(1),
+            rng,
+            instances_dirty: true,
+        }
+    });
 }
 
-fn make_colours(pixels: Vec<f32>, pointwise: bool) -> Vec<Srgb> {
-    if pointwise {
-        pointwise_colours(pixels)
-    } else {
-        individual_colours(pixels)
-    }
-} 
-
-fn pointwise_colours(pixels: Vec<f32>) -> Vec<Srgb> {
-
-    let mut colours: HashMap<String, palette::rgb::Rgb> = HashMap::new();
-    let mut rng = rand::thread_rng();
-
-    pixels.chunks(2).map(|p| {
-
-        // get the hash of the pixel
-        let p = format!("{},{}", (p[0] * 1000.0) as i32, (p[1] * 1000.0) as i32);
-
-        // get the colour from the hashmap or insert a new one
-        colours.entry(p).or_insert_with(|| {
-            Srgb {
-                red: rng.gen_range(0.0..1.0),
-                green: rng.gen_range(0.0..1.0),
-                blue: rng.gen_range(0.0..1.0),
-                standard: std::marker::PhantomData,
-            }
-            
-        }).clone()
-    }).collect()
-}
 
-fn individual_colours(pixels: Vec<f32>) -> Vec<Srgb> {
-    let mut rng = rand::thread_rng();
-    pixels
-        .chunks(3)
-        .map(|_p| {
+fn random_colours(count: usize, rng: &mut StdRng) -> Vec<Srgb> {
+    (0..count)
+        .map(|_| {
             // random colour
             Srgb {
                 red: rng.gen_range(0.0..1.0),
@@ -104,8 +129,9 @@ pub fn p_update_box(checked: bool) {
     STATE.with(|state| {
         let mut state = state.borrow_mut();
         state.pointwise = checked;
-        state.colours = make_colours(state.pixels.clone(), state.pointwise);
+        state.recolour();
     });
+    share::update(Demo::Point);
 }
 
 
@@ -113,98 +139,169 @@ pub fn p_update_box(checked: bool) {
 pub fn p_update_resolution(res: i32) {
     STATE.with(|state| {
         let mut state = state.borrow_mut();
-        state.resolution = res;
-        state.pixels = make_pixels(res);
-        state.colours = make_colours(state.pixels.clone(), state.pointwise);
+        state.grid = GridMesh::new(res);
+        state.recolour();
+    });
+    share::update(Demo::Point);
+}
+
+// resolution and pointwise, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    STATE.with(|state| {
+        let state = state.borrow();
+        let mut params = Params::new();
+        params.set("res", state.grid.resolution().to_string());
+        params.set("pointwise", share::flag(state.pointwise));
+        params
+    })
+}
+
+pub(crate) fn load_params(params: &Params) {
+    if let Some(res) = params.number::<i32>("res").filter(|res| (MIN_RESOLUTION..=MAX_RESOLUTION).contains(res)) { p_update_resolution(res); }
+    if let Some(pointwise) = params.flag("pointwise") { p_update_box(pointwise); }
+}
+
+
+
+// restart the colour source from a fixed seed so the grid can be reproduced
+pub fn reseed(seed: u64) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.rng = StdRng::seed_from_u64(seed);
+        state.recolour();
     });
 }
 
+#[wasm_bindgen]
+pub fn point_draw(canvas_id: &str) -> Result<WebGlRenderingContext, ViewerError> {
+
+    // draw one instance per cell where the context supports it
+    let gl = init_webgl_context(canvas_id)?;
+    if let Some(instancing) = Instancing::for_context(&gl) {
+        draw_instanced(&gl, &instancing)?;
+        return Ok(gl);
+    }
 
+    // otherwise upload every vertex of the grid
+    let mut renderer = WebGlRenderer::new(gl)?;
+    render(&mut renderer)?;
 
+    Ok(renderer.gl().clone())
+}
+
+// draw the demo every frame until the handle stops it
 #[wasm_bindgen]
-pub fn point_draw(canvas_id: &str) -> Result<WebGlRenderingContext, JsValue> {
-
-    // create gl context and shader program
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
-    
-    let vertex_shader_source =
-        "
-        attribute vec2 coordinates;
-        attribute vec3 colour;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_Position = vec4(coordinates, 0.0, 1.0);
-            out_colour = colour;
-        }
-        ";
-    let fragment_shader_source = 
-        "
-        precision mediump float;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_FragColor = vec4(out_colour, 1.0);
-        }
-        ";
-
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
-
-    // spawn the ARRAY_BUFFER for the vertices to use each frame
-    gl.bind_buffer(
-        WebGlRenderingContext::ARRAY_BUFFER, 
-        Some(&gl.create_buffer().unwrap())
-    );
-
-    // specify how the coordinates attribute should read from the vertex buffer
-    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates") as u32;
-    gl.vertex_attrib_pointer_with_i32(
-        coordinates_location, 
-        2, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        0
-    );
-    gl.enable_vertex_attrib_array(coordinates_location);
-
-    // specify how the colour attribute should read from from the vertex buffer
-    let colour_location = gl.get_attrib_location(&shader_program, "colour") as u32; 
-    gl.vertex_attrib_pointer_with_i32(
-        colour_location, 
-        3, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        2 * std::mem::size_of::<f32>() as i32,
-    );
-    gl.enable_vertex_attrib_array(colour_location);
-    
+pub fn point_init(canvas_id: &str) -> Result<AnimationLoop, ViewerError> {
 
-    STATE.with(|state: &RefCell<STATE>| {
+    // pick up a shared state when the demo starts
+    share::restore(Demo::Point);
+
+    let id = canvas_id.to_owned();
+    AnimationLoop::for_canvas(canvas_id, Listeners::new(), move || point_draw(&id).map(|_| ()))
+}
 
-        // get a mutable version state 
-        let state: std::cell::RefMut<STATE> = state.borrow_mut();
+// per instance offset and corner colours for every cell of the grid
+fn make_instances(state: &STATE) -> Vec<f32> {
+    let grid = &state.grid;
+    grid.cells()
+        .enumerate()
+        .flat_map(|(cell, (x, y))| {
+            let colours = (0..6).map(move |vertex| state.vertex_colour(cell, vertex));
+            [grid.to_clip(x), grid.to_clip(y)]
+                .into_iter()
+                .chain(colours.flat_map(|c| [c.red, c.green, c.blue]))
+        })
+        .collect()
+}
+
+fn draw_instanced(gl: &WebGlRenderingContext, instancing: &Instancing) -> Result<(), ViewerError> {
+
+    let shader_program: WebGlProgram = cached_program(
+        gl, INSTANCED_VERTEX_SHADER_SOURCE, INSTANCED_FRAGMENT_SHADER_SOURCE
+    )?;
+    let square_buffer = pooled_buffer(gl, "point_square")?;
+    let instance_buffer = pooled_buffer(gl, "point_instances")?;
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+
+        // only upload the grid again after it has changed
+        if state.instances_dirty {
+            gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&square_buffer));
+            gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &(unsafe { js_sys::Float32Array::view(&UNIT_SQUARE).into() }),
+                WebGlRenderingContext::STATIC_DRAW,
+            );
+
+            let instances = make_instances(&state);
+            gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&instance_buffer));
+            gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &(unsafe { js_sys::Float32Array::view(&instances).into() }),
+                WebGlRenderingContext::STATIC_DRAW,
+            );
+            state.instances_dirty = false;
+        }
 
-        // zip the two lists together
-        let data: Vec<f32> = state.pixels
-            .chunks(2)
-            .zip(state.colours.iter())
-            .flat_map(|(v, c)| vec![v[0], v[1], c.red, c.green, c.blue])
-            .collect::<Vec<f32>>();
+        let float_size = std::mem::size_of::<f32>() as i32;
+
+        // the corners advance once per vertex
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&square_buffer));
+        let corner_location = gl.get_attrib_location(&shader_program, "corner") as u32;
+        gl.vertex_attrib_pointer_with_i32(corner_location, 3, WebGlRenderingContext::FLOAT, false, 3 * float_size, 0);
+        gl.enable_vertex_attrib_array(corner_location);
+        instancing.vertex_attrib_divisor(corner_location, 0);
+
+        // the offset and colours advance once per instance
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&instance_buffer));
+        let stride = INSTANCE_STRIDE as i32 * float_size;
+        let attributes = [
+            ("offset", 2, 0),
+            ("colour0", 3, 2), ("colour1", 3, 5), ("colour2", 3, 8),
+            ("colour3", 3, 11), ("colour4", 3, 14), ("colour5", 3, 17),
+        ];
+        for (name, size, start) in attributes {
+            let location = gl.get_attrib_location(&shader_program, name) as u32;
+            gl.vertex_attrib_pointer_with_i32(
+                location, size, WebGlRenderingContext::FLOAT, false, stride, start * float_size
+            );
+            gl.enable_vertex_attrib_array(location);
+            instancing.vertex_attrib_divisor(location, 1);
+        }
+
+        let cell_size_location = gl.get_uniform_location(&shader_program, "cell_size");
+        gl.uniform1f(cell_size_location.as_ref(), state.grid.cell_size());
 
         // draw on the screen
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
         gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        let cells = state.grid.cell_count() as i32;
+        instancing.draw_arrays_instanced(WebGlRenderingContext::TRIANGLES, 0, 6, cells);
+    });
 
-        // draw shape
-        gl.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ARRAY_BUFFER,
-            &(unsafe { js_sys::Float32Array::view(&data).into() }),
-            WebGlRenderingContext::STATIC_DRAW,
-        );
-        gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, data.len() as i32 / 5);
+    Ok(())
+}
 
-    });
+// draw the coloured grid
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
+    STATE.with(|state: &RefCell<STATE>| {
+
+        // get the state for this program
+        let state: std::cell::Ref<STATE> = state.borrow();
 
-    Ok(gl)
+        // draw on the screen
+        renderer.clear([0.0, 0.0, 0.0, 0.0]);
+
+        if state.pointwise {
+            // one vertex per corner, shared between the squares around it
+            let data = state.grid.shared_vertices(|i, _| state.colours[i]);
+            renderer.upload(&data)?;
+            renderer.draw_indexed(Primitive::Triangles, state.grid.indices())
+        } else {
+            // every vertex has its own colour so none can be shared
+            let data = state.grid.cell_vertices(|cell, vertex, _| state.vertex_colour(cell, vertex));
+            renderer.draw_vertices(Primitive::Triangles, &data)
+        }
+    })
 }
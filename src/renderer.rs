@@ -0,0 +1,374 @@
+use web_sys::{WebGlBuffer, WebGlProgram, WebGlRenderingContext};
+
+use crate::error::ViewerError;
+use crate::utils::{cached_program, init_webgl_context, pooled_buffer, GlVersion};
+use crate::viewport::Viewport;
+
+// floats per vertex in the interleaved layout: x, y, r, g, b
+pub const VERTEX_STRIDE: usize = 5;
+
+// shared program reading the interleaved layout
+const VERTEX_SHADER_SOURCE: &str =
+    "
+    attribute vec2 coordinates;
+    attribute vec3 colour;
+    varying vec3 out_colour;
+
+    void main(void) {
+        gl_Position = vec4(coordinates, 0.0, 1.0);
+        out_colour = colour;
+    }
+    ";
+const FRAGMENT_SHADER_SOURCE: &str =
+    "
+    precision mediump float;
+    varying vec3 out_colour;
+
+    void main(void) {
+        gl_FragColor = vec4(out_colour, 1.0);
+    }
+    ";
+
+// the same program in GLSL ES 3.00, the version line has to come first
+const VERTEX_SHADER_SOURCE_300: &str =
+    "#version 300 es
+    in vec2 coordinates;
+    in vec3 colour;
+    out vec3 out_colour;
+
+    void main(void) {
+        gl_Position = vec4(coordinates, 0.0, 1.0);
+        out_colour = colour;
+    }
+    ";
+const FRAGMENT_SHADER_SOURCE_300: &str =
+    "#version 300 es
+    precision mediump float;
+    in vec3 out_colour;
+    out vec4 frag_colour;
+
+    void main(void) {
+        frag_colour = vec4(out_colour, 1.0);
+    }
+    ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Triangles,
+    TriangleFan,
+    TriangleStrip,
+}
+
+impl Primitive {
+    fn gl_mode(self) -> u32 {
+        match self {
+            Primitive::Triangles => WebGlRenderingContext::TRIANGLES,
+            Primitive::TriangleFan => WebGlRenderingContext::TRIANGLE_FAN,
+            Primitive::TriangleStrip => WebGlRenderingContext::TRIANGLE_STRIP,
+        }
+    }
+
+    // indices of each triangle made by `count` vertices of this primitive
+    pub fn triangles(self, count: usize) -> Vec<[usize; 3]> {
+        match self {
+            Primitive::Triangles => (0..count / 3).map(|t| [3 * t, 3 * t + 1, 3 * t + 2]).collect(),
+            Primitive::TriangleFan => (1..count.saturating_sub(1)).map(|i| [0, i, i + 1]).collect(),
+            Primitive::TriangleStrip => (0..count.saturating_sub(2)).map(|i| [i, i + 1, i + 2]).collect(),
+        }
+    }
+}
+
+// everything the demos need to put coloured triangles on the screen
+pub trait Renderer {
+    // the size of what's being drawn into, for keeping shapes the right way round
+    fn viewport(&self) -> Viewport;
+
+    fn clear(&mut self, colour: [f32; 4]);
+
+    // replace the vertex data with interleaved x, y, r, g, b floats
+    fn upload(&mut self, vertices: &[f32]) -> Result<(), ViewerError>;
+
+    // draw `count` uploaded vertices starting at `first`
+    fn draw(&mut self, primitive: Primitive, first: usize, count: usize);
+
+    // draw uploaded vertices in the order given by `indices`, so shared
+    // vertices only need uploading once
+    fn draw_indexed(&mut self, primitive: Primitive, indices: &[u32]) -> Result<(), ViewerError>;
+
+    // upload and draw every vertex in one go
+    fn draw_vertices(&mut self, primitive: Primitive, vertices: &[f32]) -> Result<(), ViewerError> {
+        self.upload(vertices)?;
+        self.draw(primitive, 0, vertices.len() / VERTEX_STRIDE);
+        Ok(())
+    }
+}
+
+// the browser backend
+#[derive(Debug, Clone)]
+pub struct WebGlRenderer {
+    gl: WebGlRenderingContext,
+    version: GlVersion,
+    program: WebGlProgram,
+    buffer: WebGlBuffer,
+    index_buffer: WebGlBuffer,
+}
+
+impl WebGlRenderer {
+    pub fn new(gl: WebGlRenderingContext) -> Result<WebGlRenderer, ViewerError> {
+        let version = GlVersion::of(&gl);
+        let program = match version {
+            GlVersion::WebGl1 => cached_program(&gl, VERTEX_SHADER_SOURCE, FRAGMENT_SHADER_SOURCE)?,
+            GlVersion::WebGl2 => cached_program(&gl, VERTEX_SHADER_SOURCE_300, FRAGMENT_SHADER_SOURCE_300)?,
+        };
+        let buffer = pooled_buffer(&gl, "renderer")?;
+        let index_buffer = pooled_buffer(&gl, "renderer_indices")?;
+        Ok(WebGlRenderer { gl, version, program, buffer, index_buffer })
+    }
+
+    pub fn for_canvas(canvas_id: &str) -> Result<WebGlRenderer, ViewerError> {
+        WebGlRenderer::new(init_webgl_context(canvas_id)?)
+    }
+
+    pub fn gl(&self) -> &WebGlRenderingContext {
+        &self.gl
+    }
+
+    pub fn version(&self) -> GlVersion {
+        self.version
+    }
+}
+
+impl Renderer for WebGlRenderer {
+    fn viewport(&self) -> Viewport {
+        Viewport::new(self.gl.drawing_buffer_width() as u32, self.gl.drawing_buffer_height() as u32)
+    }
+
+    fn clear(&mut self, colour: [f32; 4]) {
+        // the canvas may have been resized since the last frame
+        let viewport = self.viewport();
+        self.gl.viewport(0, 0, viewport.width as i32, viewport.height as i32);
+
+        self.gl.clear_color(colour[0], colour[1], colour[2], colour[3]);
+        self.gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    }
+
+    fn upload(&mut self, vertices: &[f32]) -> Result<(), ViewerError> {
+        let gl = &self.gl;
+        gl.use_program(Some(&self.program));
+
+        // fill ARRAY_BUFFER with the vertex data
+        gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.buffer));
+        gl.buffer_data_with_array_buffer_view(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &(unsafe { js_sys::Float32Array::view(vertices).into() }),
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+
+        // specify how the coordinates attribute should read from the vertex buffer
+        let stride = (VERTEX_STRIDE * std::mem::size_of::<f32>()) as i32;
+        let coordinates_location = gl.get_attrib_location(&self.program, "coordinates") as u32;
+        gl.vertex_attrib_pointer_with_i32(coordinates_location, 2, WebGlRenderingContext::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(coordinates_location);
+
+        // specify how the colour attribute should read from from the vertex buffer
+        let colour_location = gl.get_attrib_location(&self.program, "colour") as u32;
+        gl.vertex_attrib_pointer_with_i32(
+            colour_location, 3, WebGlRenderingContext::FLOAT, false,
+            stride,
+            2 * std::mem::size_of::<f32>() as i32,
+        );
+        gl.enable_vertex_attrib_array(colour_location);
+        Ok(())
+    }
+
+    fn draw(&mut self, primitive: Primitive, first: usize, count: usize) {
+        self.gl.draw_arrays(primitive.gl_mode(), first as i32, count as i32);
+    }
+
+    fn draw_indexed(&mut self, primitive: Primitive, indices: &[u32]) -> Result<(), ViewerError> {
+        let gl = &self.gl;
+        gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
+
+        // 16 bit indices work everywhere, 32 bit ones need webgl2 or an extension
+        let largest = indices.iter().copied().max().unwrap_or(0);
+        let index_type = if largest <= u16::MAX as u32 {
+            let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                &(unsafe { js_sys::Uint16Array::view(&indices).into() }),
+                WebGlRenderingContext::STATIC_DRAW,
+            );
+            WebGlRenderingContext::UNSIGNED_SHORT
+        } else {
+            if self.version == GlVersion::WebGl1
+                && gl.get_extension("OES_element_index_uint").ok().flatten().is_none()
+            {
+                return Err(ViewerError::ContextUnavailable("OES_element_index_uint".into()));
+            }
+            gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                &(unsafe { js_sys::Uint32Array::view(indices).into() }),
+                WebGlRenderingContext::STATIC_DRAW,
+            );
+            WebGlRenderingContext::UNSIGNED_INT
+        };
+
+        gl.draw_elements_with_i32(primitive.gl_mode(), indices.len() as i32, index_type, 0);
+        Ok(())
+    }
+}
+
+// a software rasterizer writing into an RGBA framebuffer, for headless rendering
+#[derive(Debug, Clone)]
+pub struct CpuRenderer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    vertices: Vec<f32>,
+}
+
+impl CpuRenderer {
+    pub fn new(width: usize, height: usize) -> CpuRenderer {
+        CpuRenderer {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // RGBA bytes, row by row from the top of the canvas
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    // run `fragment` for the center of every pixel, like a fragment shader
+    // over a full screen quad. it is given the position in clip space
+    pub fn shade(&mut self, fragment: impl Fn([f32; 2]) -> [f32; 3]) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let position = [
+                    (x as f32 + 0.5) / self.width as f32 * 2.0 - 1.0,
+                    1.0 - (y as f32 + 0.5) / self.height as f32 * 2.0,
+                ];
+                let colour = fragment(position);
+
+                let i = (y * self.width + x) * 4;
+                self.pixels[i..i + 3].copy_from_slice(&colour.map(to_byte));
+                self.pixels[i + 3] = 255;
+            }
+        }
+    }
+
+    // clip space to pixel space, with y pointing down
+    fn to_screen(&self, vertex: usize) -> [f32; 2] {
+        let v = &self.vertices[vertex * VERTEX_STRIDE..];
+        [
+            (v[0] + 1.0) * 0.5 * self.width as f32,
+            (1.0 - v[1]) * 0.5 * self.height as f32,
+        ]
+    }
+
+    fn colour(&self, vertex: usize) -> [f32; 3] {
+        let v = &self.vertices[vertex * VERTEX_STRIDE..];
+        [v[2], v[3], v[4]]
+    }
+
+    fn fill_triangle(&mut self, corners: [usize; 3]) {
+        let [a, b, c] = corners.map(|i| self.to_screen(i));
+        let colours = corners.map(|i| self.colour(i));
+
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+
+        // only visit pixels inside the bounding box
+        let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize;
+        let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as usize;
+        let max_x = (a[0].max(b[0]).max(c[0]).ceil() as usize).min(self.width);
+        let max_y = (a[1].max(b[1]).max(c[1]).ceil() as usize).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = [x as f32 + 0.5, y as f32 + 0.5];
+
+                // barycentric weights, positive inside for either winding
+                let w = [edge(b, c, p) / area, edge(c, a, p) / area, edge(a, b, p) / area];
+                if w.iter().any(|w| *w < 0.0) {
+                    continue;
+                }
+
+                let i = (y * self.width + x) * 4;
+                let pixel = &mut self.pixels[i..i + 4];
+                for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+                    *value = to_byte(
+                        w[0] * colours[0][channel]
+                        + w[1] * colours[1][channel]
+                        + w[2] * colours[2][channel]
+                    );
+                }
+                pixel[3] = 255;
+            }
+        }
+    }
+}
+
+impl Renderer for CpuRenderer {
+    fn viewport(&self) -> Viewport {
+        Viewport::new(self.width as u32, self.height as u32)
+    }
+
+    fn clear(&mut self, colour: [f32; 4]) {
+        let colour = colour.map(to_byte);
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&colour);
+        }
+    }
+
+    fn upload(&mut self, vertices: &[f32]) -> Result<(), ViewerError> {
+        self.vertices.clear();
+        self.vertices.extend_from_slice(vertices);
+        Ok(())
+    }
+
+    fn draw(&mut self, primitive: Primitive, first: usize, count: usize) {
+        let available = (self.vertices.len() / VERTEX_STRIDE).saturating_sub(first);
+        for triangle in primitive.triangles(count.min(available)) {
+            self.fill_triangle(triangle.map(|i| i + first));
+        }
+    }
+
+    fn draw_indexed(&mut self, primitive: Primitive, indices: &[u32]) -> Result<(), ViewerError> {
+        let available = self.vertices.len() / VERTEX_STRIDE;
+        for triangle in primitive.triangles(indices.len()) {
+            let corners = triangle.map(|i| indices[i] as usize);
+            if corners.iter().all(|i| *i < available) {
+                self.fill_triangle(corners);
+            }
+        }
+        Ok(())
+    }
+}
+
+// twice the signed area of the triangle a, b, p
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
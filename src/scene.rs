@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::error::ViewerError;
+use crate::{drag, history, shakes};
+
+// bumped whenever the layout below changes in a way older pages can't read
+pub const SCENE_VERSION: u32 = 1;
+
+// everything the user can edit in the drag and shakes demos
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    pub version: u32,
+    pub drag: DragScene,
+    pub shakes: ShakesScene,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DragScene {
+    pub polygon: bool,
+    pub spaces: Vec<SpaceScene>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpaceScene {
+    pub vertices: Vec<VertexScene>,
+}
+
+// the handle on a vertex, from its bottom left corner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VertexScene {
+    pub origin: [f32; 2],
+    pub size: [f32; 2],
+    pub colour: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShakesScene {
+    pub rects: Vec<RectScene>,
+    pub shake: f32,
+    pub moving: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RectScene {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+impl Scene {
+    // the scene as the demos currently have it
+    pub fn current() -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            drag: drag::to_scene(),
+            shakes: shakes::to_scene(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Scene, ViewerError> {
+        // read the version on its own first so newer scenes get a clear error
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: Option<u32>,
+        }
+        let versioned: Versioned = serde_json::from_str(json).map_err(invalid)?;
+        match versioned.version {
+            Some(SCENE_VERSION) => {}
+            Some(version) => return Err(ViewerError::InvalidScene(
+                format!("version {} is not supported, expected {}", version, SCENE_VERSION)
+            )),
+            None => return Err(ViewerError::InvalidScene("missing version".into())),
+        }
+
+        let scene: Scene = serde_json::from_str(json).map_err(invalid)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("scenes only hold plain numbers")
+    }
+
+    // check everything serde can't, so a bad scene never reaches the demos
+    pub fn validate(&self) -> Result<(), ViewerError> {
+        for (i, space) in self.drag.spaces.iter().enumerate() {
+            if space.vertices.len() < 3 {
+                return Err(ViewerError::InvalidScene(format!("space {} has fewer than 3 vertices", i)));
+            }
+            for vertex in &space.vertices {
+                let mut numbers = vertex.origin.iter().chain(&vertex.size).chain(&vertex.colour);
+                if numbers.any(|v| !v.is_finite()) {
+                    return Err(ViewerError::InvalidScene(format!("space {} has a number out of range", i)));
+                }
+                if vertex.size.iter().any(|v| *v <= 0.0) {
+                    return Err(ViewerError::InvalidScene(format!("space {} has a vertex with no size", i)));
+                }
+                if vertex.colour.iter().any(|v| !(0.0..=1.0).contains(v)) {
+                    return Err(ViewerError::InvalidScene(format!("space {} has a colour outside 0 to 1", i)));
+                }
+            }
+        }
+
+        for (i, rect) in self.shakes.rects.iter().enumerate() {
+            if rect.min.iter().chain(&rect.max).any(|v| !v.is_finite()) {
+                return Err(ViewerError::InvalidScene(format!("rect {} has a number out of range", i)));
+            }
+        }
+
+        // the shake is the top of a random range, which can't be empty
+        if !(self.shakes.shake.is_finite() && self.shakes.shake > 0.0) {
+            return Err(ViewerError::InvalidScene("shake must be above 0".into()));
+        }
+
+        Ok(())
+    }
+
+    // replace the demos' state with this scene
+    pub fn apply(&self) {
+        drag::load_scene(&self.drag);
+        shakes::load_scene(&self.shakes);
+
+        // the history talks about spaces and rects that no longer exist
+        history::clear_history();
+    }
+}
+
+fn invalid(error: serde_json::Error) -> ViewerError {
+    ViewerError::InvalidScene(error.to_string())
+}
+
+// the drag and shakes layouts as json, for saving and loading in other pages
+#[wasm_bindgen]
+pub fn export_scene() -> String {
+    Scene::current().to_json()
+}
+
+// load a layout saved by `export_scene`, leaving everything as it was if it isn't valid
+#[wasm_bindgen]
+pub fn import_scene(json: &str) -> Result<(), ViewerError> {
+    Scene::from_json(json)?.apply();
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{point, shakes, sin_wave};
+
+thread_local! {
+    // the seed every random demo was last started from
+    static SEED: Cell<u64> = Cell::new(seed_from_url().unwrap_or_else(rand::random));
+}
+
+// read `?seed=` from the page url, if there is one
+#[cfg(target_arch = "wasm32")]
+fn seed_from_url() -> Option<u64> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()?
+        .get("seed")?
+        .parse()
+        .ok()
+}
+
+// there is no page url outside the browser
+#[cfg(not(target_arch = "wasm32"))]
+fn seed_from_url() -> Option<u64> {
+    None
+}
+
+// the seed used by the random colour and shake sources
+#[wasm_bindgen]
+pub fn current_seed() -> u64 {
+    SEED.with(|seed| seed.get())
+}
+
+// restart every random demo from this seed so a frame can be replayed
+#[wasm_bindgen]
+pub fn set_seed(seed: u64) {
+    SEED.with(|current| current.set(seed));
+    point::reseed(seed);
+    sin_wave::reseed(seed);
+    shakes::reseed(seed);
+}
@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+
+use web_sys::{WebGlFramebuffer, WebGlRenderbuffer, WebGlRenderingContext, WebGlTexture};
+
+use crate::error::ViewerError;
+use crate::math::{self, Mat4, Vec3};
+
+// the sizes a shadow map can be, in texels along each side. the largest is the
+// page's finest setting, three maps of it are already 48MB of texture
+pub const MIN_SHADOW_RESOLUTION: u32 = 64;
+pub const MAX_SHADOW_RESOLUTION: u32 = 2048;
+pub const DEFAULT_SHADOW_RESOLUTION: u32 = 1024;
+
+// how far behind the nearest depth a point has to be to count as shadowed
+pub const DEFAULT_SHADOW_BIAS: f32 = 0.0002;
+
+// how far the light's view of the scene reaches
+const SHADOW_NEAR: f32 = 1.0;
+const SHADOW_FAR: f32 = 500.0;
+
+// depth from the light, packed into the colour channels so webgl1 needs no extension
+pub const DEPTH_VERTEX_SHADER_SOURCE: &str =
+    "
+    attribute vec3 position;
+    uniform mat4 model;
+    uniform mat4 light_view_projection;
+
+    void main(void) {
+        gl_Position = light_view_projection * model * vec4(position, 1.0);
+    }
+    ";
+
+// kept in step with `pack_depth`
+pub const DEPTH_FRAGMENT_SHADER_SOURCE: &str =
+    "
+    #ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+    #else
+    precision mediump float;
+    #endif
+
+    void main(void) {
+        vec4 bits = fract(gl_FragCoord.z * vec4(1.0, 255.0, 65025.0, 16581375.0));
+        gl_FragColor = bits - bits.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+    }
+    ";
+
+// keep a requested resolution inside the sizes a shadow map can be
+pub fn clamp_resolution(resolution: u32) -> u32 {
+    resolution.clamp(MIN_SHADOW_RESOLUTION, MAX_SHADOW_RESOLUTION)
+}
+
+// a 0 to 1 depth spread over four 8 bit channels, most significant first
+pub fn pack_depth(depth: f32) -> [f32; 4] {
+    let bits = [1.0, 255.0, 65025.0, 16581375.0].map(|scale: f32| (depth * scale).fract());
+    [
+        bits[0] - bits[1] / 255.0,
+        bits[1] - bits[2] / 255.0,
+        bits[2] - bits[3] / 255.0,
+        bits[3],
+    ]
+}
+
+pub fn unpack_depth(rgba: [f32; 4]) -> f32 {
+    rgba[0] + rgba[1] / 255.0 + rgba[2] / 65025.0 + rgba[3] / 16581375.0
+}
+
+// the light's view of the scene, wide enough to take in its whole beam
+pub fn light_view_projection(position: Vec3, direction: Vec3, angle: f32) -> Mat4 {
+    let fov = (2.0 * angle + 0.1).min(3.0);
+    let projection = math::perspective(fov, 1.0, SHADOW_NEAR, SHADOW_FAR);
+
+    // look_at needs an up that isn't along the beam
+    let up = if math::cross([0.0, 1.0, 0.0], direction) == [0.0; 3] { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let view = math::look_at(position, math::add(position, direction), up);
+    math::multiply(&projection, &view)
+}
+
+// how lit a point is, 0 to 1, from a 3x3 block of depths around it. kept in step
+// with the lighting shader, `closest` reads the map in 0 to 1 texture space
+pub fn pcf_visibility(closest: impl Fn(f32, f32) -> f32, uv: [f32; 2], depth: f32, bias: f32, texel: f32) -> f32 {
+    let mut lit = 0.0;
+    for x in -1..=1 {
+        for y in -1..=1 {
+            if depth - bias <= closest(uv[0] + x as f32 * texel, uv[1] + y as f32 * texel) {
+                lit += 1.0;
+            }
+        }
+    }
+    lit / 9.0
+}
+
+// a texture to render one light's depths into, with a depth buffer for the pass itself
+#[derive(Clone)]
+pub struct ShadowMap {
+    pub framebuffer: WebGlFramebuffer,
+    pub texture: WebGlTexture,
+    depth: WebGlRenderbuffer,
+    pub resolution: u32,
+}
+
+impl ShadowMap {
+    pub fn new(gl: &WebGlRenderingContext, resolution: u32) -> Result<ShadowMap, ViewerError> {
+        let size = resolution as i32;
+        let unavailable = |what: &str| ViewerError::ContextUnavailable(what.into());
+
+        let texture = gl.create_texture().ok_or_else(|| unavailable("shadow map texture"))?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            size,
+            size,
+            0,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            None,
+        ).map_err(|_| unavailable("shadow map texture"))?;
+
+        // packed depths can't be blended, and any size has to work without mipmaps
+        for (parameter, value) in [
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+        ] {
+            gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+
+        let depth = gl.create_renderbuffer().ok_or_else(|| unavailable("shadow map depth buffer"))?;
+        gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(&depth));
+        gl.renderbuffer_storage(WebGlRenderingContext::RENDERBUFFER, WebGlRenderingContext::DEPTH_COMPONENT16, size, size);
+
+        let framebuffer = gl.create_framebuffer().ok_or_else(|| unavailable("shadow map framebuffer"))?;
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        gl.framebuffer_texture_2d(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::COLOR_ATTACHMENT0,
+            WebGlRenderingContext::TEXTURE_2D,
+            Some(&texture),
+            0,
+        );
+        gl.framebuffer_renderbuffer(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::DEPTH_ATTACHMENT,
+            WebGlRenderingContext::RENDERBUFFER,
+            Some(&depth),
+        );
+        let complete = gl.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER)
+            == WebGlRenderingContext::FRAMEBUFFER_COMPLETE;
+
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, None);
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, None);
+
+        let map = ShadowMap { framebuffer, texture, depth, resolution };
+        if !complete {
+            map.delete(gl);
+            return Err(unavailable("shadow map framebuffer"));
+        }
+        Ok(map)
+    }
+
+    fn delete(&self, gl: &WebGlRenderingContext) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_renderbuffer(Some(&self.depth));
+        gl.delete_texture(Some(&self.texture));
+    }
+}
+
+thread_local! {
+    // the maps from the last frame, and the context they belong to
+    static SHADOW_MAPS: RefCell<Option<(WebGlRenderingContext, Vec<ShadowMap>)>> = const { RefCell::new(None) };
+}
+
+// the largest texture and depth buffer this context can render to
+fn max_render_size(gl: &WebGlRenderingContext) -> u32 {
+    [WebGlRenderingContext::MAX_TEXTURE_SIZE, WebGlRenderingContext::MAX_RENDERBUFFER_SIZE]
+        .into_iter()
+        .filter_map(|parameter| gl.get_parameter(parameter).ok()?.as_f64())
+        .fold(MAX_SHADOW_RESOLUTION, |max, size| max.min(size as u32))
+}
+
+// one map per light at this resolution, or as near as the device can render to,
+// only rebuilt when the count or resolution changes
+pub fn shadow_maps(gl: &WebGlRenderingContext, count: usize, resolution: u32) -> Result<Vec<ShadowMap>, ViewerError> {
+    let resolution = resolution.min(max_render_size(gl)).max(1);
+    SHADOW_MAPS.with(|maps| {
+        let mut maps = maps.borrow_mut();
+        if let Some((context, current)) = maps.as_ref() {
+            if context == gl && current.len() == count && current.iter().all(|map| map.resolution == resolution) {
+                return Ok(current.clone());
+            }
+            if context == gl {
+                current.iter().for_each(|map| map.delete(gl));
+            }
+        }
+        *maps = None;
+
+        let created = (0..count).map(|_| ShadowMap::new(gl, resolution)).collect::<Result<Vec<_>, _>>()?;
+        *maps = Some((gl.clone(), created.clone()));
+        Ok(created)
+    })
+}
@@ -1,112 +1,318 @@
 use std::cell::RefCell;
 
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use wasm_bindgen::prelude::*;
-use web_sys::{window, HtmlElement, HtmlInputElement, MouseEvent, WebGlRenderingContext};
+use web_sys::{Event, HtmlElement, HtmlInputElement};
 use euclid::{self, Box2D};
 
-extern crate js_sys;
+use crate::error::ViewerError;
+use crate::history::{self, Command};
+use crate::input::{self, Phase, PointerEvent};
+use crate::listeners::Listeners;
+use crate::animation::AnimationLoop;
+use crate::clock::{Clock, FixedStep, TICK};
+use crate::physics::{self, Body, World, FIXED_STEP};
+use crate::scene::{RectScene, ShakesScene};
+use crate::share::{self, Demo, Params};
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::seed::current_seed;
+use crate::utils::get_element;
 
-use crate::utils::default_gl;
+// how hard the shake slider kicks boxes around in physics mode, per second at a shake of 1
+const SHAKE_IMPULSE: f64 = 4.0;
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone)]
 struct STATE {
-    rects: Vec<Box2D<f64, f64>>,
+    rects: Vec<Body>,
     x: f32,
     c: f32,
     moving: bool,
+    rng: StdRng,
+    // boxes fall and bounce off each other, rather than jittering in place
+    physics: bool,
+    world: World,
+    stepper: FixedStep,
+    clock: Clock,
 }
 
 // Initialize the state
 thread_local! {
     static STATE: RefCell<STATE> = RefCell::new(STATE {
         rects: vec![
-            Box2D::new(euclid::point2(0.7, 0.3), euclid::point2(-0.5, -0.5)),
-            Box2D::new(euclid::point2(0.0, 0.0), euclid::point2(0.5, 0.5)),
+            Body::at_rest(Box2D::new(euclid::point2(0.7, 0.3), euclid::point2(-0.5, -0.5))),
+            Body::at_rest(Box2D::new(euclid::point2(0.0, 0.0), euclid::point2(0.5, 0.5))),
         ],
         x: 0.0,
         c: 1.0,
         moving: true,
+        rng: StdRng::seed_from_u64(current_seed()),
+        physics: false,
+        world: World::default(),
+        stepper: FixedStep::new(FIXED_STEP),
+        clock: Clock::new(),
     });
 }
 
+// a rect added by clicking, or taken away by shift clicking
+struct AddRect {
+    index: usize,
+    rect: Box2D<f64, f64>,
+}
+
+struct RemoveRect {
+    index: usize,
+    rect: Box2D<f64, f64>,
+}
+
+fn insert_rect(index: usize, rect: Box2D<f64, f64>) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let index = index.min(state.rects.len());
+        state.rects.insert(index, Body::at_rest(rect));
+    });
+    share::update(Demo::Shakes);
+}
+
+fn take_rect(index: usize) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if index < state.rects.len() {
+            state.rects.remove(index);
+        }
+    });
+    share::update(Demo::Shakes);
+}
+
+impl Command for AddRect {
+    fn undo(&self) { take_rect(self.index); }
+    fn redo(&self) { insert_rect(self.index, self.rect); }
+}
+
+impl Command for RemoveRect {
+    fn undo(&self) { insert_rect(self.index, self.rect); }
+    fn redo(&self) { take_rect(self.index); }
+}
+
 #[wasm_bindgen]
-pub fn shakes_init() {
-    
-    let document = window().unwrap().document().unwrap();
+pub fn shakes_rect_count() -> usize {
+    STATE.with(|state| state.borrow().rects.len())
+}
+
+// let the rects fall under gravity and bounce off the walls and each other,
+// every rect starts from rest either way
+#[wasm_bindgen]
+pub fn shakes_set_physics(enabled: bool) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.physics = enabled;
+        state.stepper = FixedStep::new(FIXED_STEP);
+        for body in state.rects.iter_mut() {
+            body.velocity = euclid::vec2(0.0, 0.0);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn shakes_physics() -> bool {
+    STATE.with(|state| state.borrow().physics)
+}
+
+// the rect at an index as it is now, from its min to its max corner
+pub fn shakes_rect(index: usize) -> Option<Box2D<f64, f64>> {
+    STATE.with(|state| state.borrow().rects.get(index).map(|body| body.rect))
+}
+
+// move the rects on by `elapsed` seconds. the jitter runs at 0.05 a tick, physics in
+// whole fixed steps with the shake slider kicking every rect about as it goes.
+// nothing happens while moving is unticked
+pub fn shakes_step(elapsed: f64) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let state = &mut *state;
+        if !state.moving { return; }
+        if !state.physics {
+            state.x += 0.05 * (elapsed / TICK) as f32;
+            return;
+        }
+
+        let dt = state.stepper.step;
+        let strength = state.c as f64 * SHAKE_IMPULSE;
+        for _ in 0..state.stepper.advance(elapsed) {
+            for body in state.rects.iter_mut() {
+                body.velocity += physics::shake(&mut state.rng, strength, dt);
+            }
+            state.world.step(&mut state.rects, dt);
+        }
+    });
+}
+
+// the rects and settings as they are saved in a scene
+pub(crate) fn to_scene() -> ShakesScene {
+    STATE.with(|state| {
+        let state = state.borrow();
+        ShakesScene {
+            rects: state.rects.iter()
+                .map(|body| RectScene { min: body.rect.min.to_array(), max: body.rect.max.to_array() })
+                .collect(),
+            shake: state.c,
+            moving: state.moving,
+        }
+    })
+}
+
+pub(crate) fn load_scene(scene: &ShakesScene) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.rects = scene.rects.iter()
+            .map(|rect| Body::at_rest(Box2D::new(rect.min.into(), rect.max.into())))
+            .collect();
+        state.c = scene.shake;
+        state.moving = scene.moving;
+    });
+    share::update(Demo::Shakes);
+}
+
+// the corners of every rect, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    let rects: Vec<Vec<f32>> = STATE.with(|state| {
+        state.borrow().rects.iter()
+            .map(|body| body.rect)
+            .map(|rect| [rect.min.x, rect.min.y, rect.max.x, rect.max.y].map(|v| v as f32).to_vec())
+            .collect()
+    });
+    let mut params = Params::new();
+    params.set("rects", share::groups(&rects));
+    params
+}
+
+pub(crate) fn load_params(params: &Params) {
+    let Some(rects) = params.groups("rects").filter(|rects| rects.iter().all(|r| r.len() == 4)) else {
+        return;
+    };
+    STATE.with(|state| {
+        state.borrow_mut().rects = rects.iter()
+            .map(|r| Body::at_rest(Box2D::new(
+                euclid::point2(r[0] as f64, r[1] as f64),
+                euclid::point2(r[2] as f64, r[3] as f64),
+            )))
+            .collect();
+    });
+
+    // the history talks about rects that no longer exist
+    history::clear_history();
+}
+
+// start the demo on the `user_input` canvas, the handle stops and restarts it
+#[wasm_bindgen]
+pub fn shakes_init() -> Result<AnimationLoop, ViewerError> {
+    let mut listeners = Listeners::new();
 
     // add moving checkbox
-    let moving_input: HtmlInputElement = document.get_element_by_id("move").unwrap().dyn_into().unwrap();
+    let moving_input: HtmlInputElement = get_element("move", "input")?;
     let moving_read = moving_input.clone();
-    let closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+    listeners.listen(&moving_input, "change", move |_: Event| {
         web_sys::console::log_1(&moving_read.checked().into());
         STATE.with(|state| {
             let mut state = state.borrow_mut();
             state.moving = moving_read.checked();
         });
-    }));
-    moving_input.set_onchange(Some(closure.as_ref().unchecked_ref()));
-    closure.forget();
+    })?;
 
     // add shake slider
-    let shake_input: HtmlInputElement = document.get_element_by_id("shake").unwrap().dyn_into().unwrap();
-    let shake_output: HtmlElement = document.get_element_by_id("shake_output").unwrap().dyn_into().unwrap();
+    let shake_input: HtmlInputElement = get_element("shake", "input")?;
+    let shake_output: HtmlElement = get_element("shake_output", "html element")?;
     let shake_read = shake_input.clone();
-    let closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+    listeners.listen(&shake_input, "input", move |_: Event| {
+        // a value that isn't a number leaves the shake as it was
+        let Ok(shake) = shake_read.value().parse::<f32>() else { return; };
         STATE.with(|state| {
             let mut state = state.borrow_mut();
-            state.c = shake_read.value().parse::<f32>().unwrap() / 10.0;
+            state.c = shake / 10.0;
         });
         shake_output.set_inner_html(&("shake: ".to_owned() + &shake_read.value()));
-    }));
-    shake_input.set_oninput(Some(closure.as_ref().unchecked_ref()));
-    closure.forget();
-
-    // do mouse input stuff
-    let canvas: HtmlElement = document.get_element_by_id("user_input").unwrap().dyn_into().unwrap();
-    let bounding_rect = canvas.get_bounding_client_rect();
-
-    let closure: Closure<dyn FnMut(MouseEvent)> = Closure::wrap(Box::new(move |event: MouseEvent| {
-        
-        let mouse_x = -1.0 + 2.0 * (event.client_x() as f64 - bounding_rect.x()) / bounding_rect.width();
-        let mouse_y = -1.0 + 2.0 * (event.client_y() as f64 - bounding_rect.y()) / bounding_rect.height();
-
-        // add a new rect at this position
-        let new_rect = Box2D::new(
-            euclid::point2(mouse_x, -mouse_y), 
-            euclid::point2(mouse_x + 0.05, -mouse_y + 0.05)
-        );
-        STATE.with(|state| {
-            let mut state = state.borrow_mut();
-            state.rects.push(new_rect);
-        });
-    }));
+    })?;
+
+    // add physics checkbox
+    let physics_input: HtmlInputElement = get_element("physics", "input")?;
+    let physics_read = physics_input.clone();
+    listeners.listen(&physics_input, "change", move |_: Event| {
+        shakes_set_physics(physics_read.checked());
+    })?;
+
+    // clicks and taps add rects
+    input::listen(&mut listeners, "user_input", shakes_pointer)?;
+
+    // ctrl+z and ctrl+shift+z step through the edits
+    history::listen_for_shortcuts(&mut listeners)?;
+
+    // pick up shared rects
+    share::restore(Demo::Shakes);
+
+    // start animation loop, moving on by however long each frame took
+    let mut renderer = WebGlRenderer::for_canvas("user_input")?;
+    AnimationLoop::for_canvas("user_input", listeners, move || {
+        let elapsed = STATE.with(|state| state.borrow_mut().clock.tick());
+        shakes_step(elapsed);
+        render(&mut renderer)
+    })
+}
+
 
-    canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref()).unwrap();
+// every finger down adds a rect, so several can be placed at once
+pub fn shakes_pointer(event: &PointerEvent) {
+    if event.phase == Phase::Down && event.pointer.button == 0 {
+        let [x, y] = event.pointer.position;
+        shakes_mouse_down(x as f64, y as f64, event.pointer.shift);
+    }
+}
 
-    closure.forget(); // Keep the closure alive
+// add a new rect at this position, or remove the topmost one under it
+pub fn shakes_mouse_down(x: f64, y: f64, remove: bool) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
 
-    // start animation loop
-    user_draw(default_gl());
+        if remove {
+            // rects can be stored with their corners either way round
+            let under = state.rects.iter().map(|body| body.rect).rposition(|rect| {
+                let (x0, x1) = (rect.min.x.min(rect.max.x), rect.min.x.max(rect.max.x));
+                let (y0, y1) = (rect.min.y.min(rect.max.y), rect.min.y.max(rect.max.y));
+                (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+            });
+            if let Some(index) = under {
+                let rect = state.rects.remove(index).rect;
+                history::record(RemoveRect { index, rect });
+            }
+            return;
+        }
 
+        let rect = Box2D::new(euclid::point2(x, y), euclid::point2(x + 0.05, y + 0.05));
+        state.rects.push(Body::at_rest(rect));
+        history::record(AddRect { index: state.rects.len() - 1, rect });
+    });
+    share::update(Demo::Shakes);
 }
 
+// restart the shake source from a fixed seed so the jitter can be reproduced
+pub fn reseed(seed: u64) {
+    STATE.with(|state| state.borrow_mut().rng = StdRng::seed_from_u64(seed));
+}
 
-fn user_draw(gl: WebGlRenderingContext) {
+// draw every rect, shaking them if they are moving
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
 
-    gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    renderer.clear([0.0, 0.0, 0.0, 0.0]);
     
-    let mut rng = thread_rng();
     let mut i = 0;
 
     STATE.with(|state| {
 
         let mut state = state.borrow_mut();
-        state.x += 0.05;
+        let state = &mut *state;
 
         // draw rects
-        for rect in state.rects.iter() {
+        for rect in state.rects.iter().map(|body| body.rect) {
             i += 1;
             let bottom_left: euclid::Point2D<f64, f64> = rect.min;
             let top_right: euclid::Point2D<f64, f64> = rect.max;
@@ -120,34 +326,19 @@ fn user_draw(gl: WebGlRenderingContext) {
                 top_right.x as f32, top_right.y as f32, 1.0, 1.0, 1.0,
             ];
             
-            if state.moving {
+            // physics mode moves the rects themselves
+            if state.moving && !state.physics {
                 data = data.iter().map(|x| 
                     *x 
-                    + ((state.x + rng.gen_range(0.0..state.c)).sin() / 5.0)
+                    + ((state.x + state.rng.gen_range(0.0..state.c)).sin() / 5.0)
                     * if i % 2 == 0 { 1.0 } else { -1.0 }
                     - 0.1
                 ).collect::<Vec<f32>>();
             }
 
-            // fill ARRAY_BUFFER with the vertex data
-            gl.buffer_data_with_array_buffer_view(
-                WebGlRenderingContext::ARRAY_BUFFER,
-                &(unsafe { js_sys::Float32Array::view(&data).into() }),
-                WebGlRenderingContext::STATIC_DRAW,
-            );
-        
             // draw on the screen
-            gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, 4);
+            renderer.draw_vertices(Primitive::TriangleFan, &data)?;
         }
-        
-
-        // request for another animation frame, with changes to state if needed
-        let closure: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
-                user_draw(gl.clone());
-        }));
-        web_sys::window().unwrap()
-            .request_animation_frame(closure.as_ref().unchecked_ref()).unwrap();
-        closure.forget();
-
-    });
+        Ok(())
+    })
 }
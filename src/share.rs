@@ -0,0 +1,191 @@
+use std::cell::Cell;
+use std::str::FromStr;
+
+use crate::{drag, gradient, lights, point, shakes, sin_wave, special};
+
+// every demo that can be shared through the url hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Demo {
+    Special,
+    Gradient,
+    Point,
+    SinWave,
+    Drag,
+    Shakes,
+    Lights,
+}
+
+impl Demo {
+    const ALL: [Demo; 7] = [
+        Demo::Special, Demo::Gradient, Demo::Point, Demo::SinWave, Demo::Drag, Demo::Shakes, Demo::Lights,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Demo::Special => "special",
+            Demo::Gradient => "gradient",
+            Demo::Point => "point",
+            Demo::SinWave => "sin_wave",
+            Demo::Drag => "drag",
+            Demo::Shakes => "shakes",
+            Demo::Lights => "lights",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Demo> {
+        Demo::ALL.into_iter().find(|demo| demo.name() == name)
+    }
+}
+
+// the key=value pairs after the demo name, like `#point/res=10&pointwise=1`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+    pairs: Vec<(String, String)>,
+}
+
+impl Params {
+    pub fn new() -> Params {
+        Params::default()
+    }
+
+    fn parse(params: &str) -> Params {
+        Params {
+            pairs: params.split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        self.pairs.retain(|(k, _)| k != key);
+        self.pairs.push((key.to_owned(), value.into()));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    // a value that parses, anything else is left at what the demo already has
+    pub fn number<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    // groups of numbers, `;` between groups and `,` between numbers
+    pub fn groups(&self, key: &str) -> Option<Vec<Vec<f32>>> {
+        let value = self.get(key)?;
+        if value.is_empty() { return Some(Vec::new()); }
+        value.split(';')
+            .map(|group| group.split(',').map(|v| v.parse::<f32>().ok().filter(|v| v.is_finite())).collect())
+            .collect()
+    }
+
+    fn encode(&self) -> String {
+        self.pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")
+    }
+}
+
+// short enough for a url, three decimal places is finer than a pixel
+pub fn number(value: f32) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".into() } else { text.into() }
+}
+
+pub fn flag(value: bool) -> &'static str {
+    if value { "1" } else { "0" }
+}
+
+pub fn groups(groups: &[Vec<f32>]) -> String {
+    groups.iter()
+        .map(|group| group.iter().map(|v| number(*v)).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+thread_local! {
+    // the demo on this page, the only one that writes to the hash
+    static ACTIVE: Cell<Option<Demo>> = const { Cell::new(None) };
+}
+
+// the state of a demo as a hash, without the leading `#`
+pub fn encode(demo: Demo) -> String {
+    let params = match demo {
+        Demo::Special => special::share_params(),
+        Demo::Gradient => gradient::share_params(),
+        Demo::Point => point::share_params(),
+        Demo::SinWave => sin_wave::share_params(),
+        Demo::Drag => drag::share_params(),
+        Demo::Shakes => shakes::share_params(),
+        Demo::Lights => lights::share_params(),
+    };
+    format!("{}/{}", demo.name(), params.encode())
+}
+
+// load a hash into the demo it names, returns which demo that was
+pub fn decode(hash: &str) -> Option<Demo> {
+    let hash = hash.strip_prefix('#').unwrap_or(hash);
+    let (name, params) = hash.split_once('/').unwrap_or((hash, ""));
+    let demo = Demo::from_name(name)?;
+    let params = Params::parse(params);
+
+    match demo {
+        Demo::Special => special::load_params(&params),
+        Demo::Gradient => gradient::load_params(&params),
+        Demo::Point => point::load_params(&params),
+        Demo::SinWave => sin_wave::load_params(&params),
+        Demo::Drag => drag::load_params(&params),
+        Demo::Shakes => shakes::load_params(&params),
+        Demo::Lights => lights::load_params(&params),
+    }
+    Some(demo)
+}
+
+// called when a demo starts on a page, loads its state from the hash once
+pub fn restore(demo: Demo) {
+    if ACTIVE.with(|active| active.replace(Some(demo))) == Some(demo) {
+        return;
+    }
+    if let Some(hash) = read_hash() {
+        decode(&hash);
+    }
+}
+
+// called after every change to a demo, keeps the hash in step with it
+pub fn update(demo: Demo) {
+    if ACTIVE.with(|active| active.get()) == Some(demo) {
+        write_hash(&encode(demo));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_hash() -> Option<String> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    (!hash.is_empty()).then_some(hash)
+}
+
+// replace the hash without adding to the back button's history
+#[cfg(target_arch = "wasm32")]
+fn write_hash(hash: &str) {
+    let Some(window) = web_sys::window() else { return; };
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&format!("#{}", hash)));
+    }
+}
+
+// there is no page url outside the browser
+#[cfg(not(target_arch = "wasm32"))]
+fn read_hash() -> Option<String> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_hash(_hash: &str) {}
@@ -1,73 +1,145 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlProgram, WebGlRenderingContext};
+use web_sys::{WebGlRenderingContext, WebGlTexture};
 use palette::Srgb;
-use std::{cell::RefCell, collections::HashMap};
-
-use rand::Rng;
-
-extern crate js_sys;
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::animation::AnimationLoop;
+use crate::clock::now;
+use crate::error::ViewerError;
+use crate::grid::GridMesh;
+use crate::input::{self, Phase, PointerEvent};
+use crate::listeners::Listeners;
+use crate::renderer::{CpuRenderer, Primitive, Renderer, WebGlRenderer};
+use crate::seed::current_seed;
+use crate::share::{self, Demo, Params};
+use crate::utils::{cached_program, static_buffer};
+
+// a full screen quad, the wave itself is worked out per fragment
+const WAVE_VERTEX_SHADER_SOURCE: &str =
+    "
+    attribute vec2 coordinates;
+    varying vec2 position;
+
+    void main(void) {
+        gl_Position = vec4(coordinates, 0.0, 1.0);
+        position = coordinates;
+    }
+    ";
+
+// kept in step with `wave` and `tint`. the tint texture holds the red and green
+// of each grid corner, one texel per corner with x down the rows
+const WAVE_FRAGMENT_SHADER_SOURCE: &str =
+    "
+    precision highp float;
+    uniform float wavelength;
+    uniform vec2 center;
+    uniform float resolution;
+    uniform bool quantised;
+    uniform sampler2D tint;
+    varying vec2 position;
+
+    void main(void) {
+        vec2 p = position;
+
+        // the nearest corner of the cpu grid
+        vec2 corner = floor((p + 1.0) * resolution / 2.0 + 0.5);
+        if (quantised) {
+            p = corner / resolution * 2.0 - 1.0;
+        }
 
-use crate::utils::{init_webgl_context, link_shaders};
+        float value = (1.0 + cos(wavelength * distance(p, center))) / 2.0;
+        vec2 rg = texture2D(tint, (corner.yx + 0.5) / (resolution + 1.0)).rg;
+        gl_FragColor = vec4(rg, value, 1.0);
+    }
+    ";
+
+const FULL_SCREEN_QUAD: [f32; 8] = [
+    -1.0, -1.0,
+    1.0, -1.0,
+    -1.0, 1.0,
+    1.0, 1.0,
+];
+
+// the resolutions a link can ask for, the slider's range and the resolution the
+// wave starts at. anything else is ignored, so a link can't ask for a grid too
+// big to build
+const MIN_RESOLUTION: i32 = 1;
+const MAX_RESOLUTION: i32 = 100;
+
+// how long the center has to rest, in milliseconds, before the hash follows it.
+// writing it on every move runs into the browser's limit on history updates
+const SHARE_DELAY: f64 = 500.0;
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 struct STATE {
-    resolution: i32,
     wavelength: f32,
-    pixels: Vec<f32>,
+    grid: GridMesh,
     colours: Vec<Srgb>,
-    gl: WebGlRenderingContext,
+    renderer: Option<WebGlRenderer>,
+    // the red and green of `colours` for the gpu path, and whether it needs uploading again
+    tint: Option<WebGlTexture>,
+    tint_dirty: bool,
     center: (f32, f32),
+    // when a moved center is due to be written into the hash
+    share_due: Option<f64>,
+    rng: StdRng,
+    colours_dirty: bool,
+    gpu: bool,
+    quantised: bool,
+}
+
+impl STATE {
+    // recompute the wave colours for the current corners and center
+    fn recolour(&mut self) {
+        self.colours = pointwise_colours(
+            &self.grid, self.wavelength, self.center.0, self.center.1, &mut self.rng
+        );
+        self.colours_dirty = false;
+        self.tint_dirty = true;
+    }
 }
 
 
 // Initialize the state
 thread_local! {
-    static STATE: RefCell<STATE> = RefCell::new(STATE {
-        resolution: 100,
-        wavelength: 10.0,
-        pixels: make_pixels(20),
-        colours: pointwise_colours(make_pixels(1), 10.0, 0.0, 0.0),
-        center: (0.0, 0.0),
-        gl: init_gl("sin_wave"),
+    static STATE: RefCell<STATE> = RefCell::new({
+        let mut rng = StdRng::seed_from_u64(current_seed());
+        STATE {
+            wavelength: 10.0,
+            colours: pointwise_colours(&GridMesh::new(100), 10.0, 0.0, 0.0, &mut rng),
+            grid: GridMesh::new(100),
+            center: (0.0, 0.0),
+            share_due: None,
+            renderer: None,
+            tint: None,
+            tint_dirty: true,
+            rng,
+            colours_dirty: true,
+            gpu: true,
+            quantised: true,
+        }
     });
 }
 
 
-// return two triangles that make a square with coords normalized to -1.0 to 1.0
-fn make_square(res: i32, x: i32, y: i32) -> Vec<f32> {
-    vec![
-        x, y, x + 1, y, x, y + 1, x, y + 1, x + 1, y, x + 1, y + 1
-    ].iter().map(|v| (*v as f32 / res as f32) * 2.0 - 1.0).collect::<Vec<f32>>()
-}
-
-// return a grid of pixels
-fn make_pixels(resolution: i32) -> Vec<f32> {
-    (0..resolution*2)
-        .flat_map(|x| (0..resolution*2)
-        .flat_map(move |y| make_square(resolution, x, y)))
-        .collect()
-}
-
-fn pointwise_colours(pixels: Vec<f32>, w: f32, x: f32, y: f32) -> Vec<Srgb> {
-
-    let mut rng = rand::thread_rng();
-    let mut colours: HashMap<String, palette::rgb::Rgb> = HashMap::new();
-
-    pixels.chunks(2).map(|p| {
+// one colour per corner, so the wave is exact at every corner
+fn pointwise_colours(grid: &GridMesh, w: f32, x: f32, y: f32, rng: &mut StdRng) -> Vec<Srgb> {
+    (0..grid.corner_count()).map(|i| {
 
+        let p = grid.corner(i);
         let dist = ((p[0] - x).powi(2) + (p[1] - y).powi(2)).sqrt();
         let val = (1.0 + (w * dist).cos()) / 2.0;
 
-        let p = format!("{},{}", (p[0] * 1000.0) as i32, (p[1] * 1000.0) as i32);
-        colours.entry(p).or_insert_with(|| {
-            Srgb {
-                red: rng.gen_range(0.0..0.2),
-                green: rng.gen_range(0.0..0.2),
-                blue: val,
-                standard: std::marker::PhantomData,
-            }
-        }).clone()
+        Srgb {
+            red: rng.gen_range(0.0..0.2),
+            green: rng.gen_range(0.0..0.2),
+            blue: val,
+            standard: std::marker::PhantomData,
+        }
 
     }).collect()
 }
@@ -77,10 +149,10 @@ fn pointwise_colours(pixels: Vec<f32>, w: f32, x: f32, y: f32) -> Vec<Srgb> {
 pub fn s_update_resolution(res: i32) {
     STATE.with(|state| {
         let mut state = state.borrow_mut();
-        state.resolution = res;
-        state.pixels = make_pixels(res);
-        state.colours = pointwise_colours(state.pixels.clone(), state.wavelength, state.center.0, state.center.1);
+        state.grid = GridMesh::new(res);
+        state.colours_dirty = true;
     });
+    share::update(Demo::SinWave);
 }
 
 #[wasm_bindgen]
@@ -88,117 +160,290 @@ pub fn s_update_wavelength(w: f32) {
     STATE.with(|state| {
         let mut state = state.borrow_mut();
         state.wavelength = w;
-        state.colours = pointwise_colours(state.pixels.clone(), state.wavelength, state.center.0, state.center.1);
+        state.colours_dirty = true;
     });
+    share::update(Demo::SinWave);
 }
 
 #[wasm_bindgen]
 pub fn s_mouse_move(x: f32, y: f32) {
-    web_sys::console::log_1(&format!("x: {}, y: {}", x, y).into());
     STATE.with(|state| {
         let mut state = state.borrow_mut();
         state.center = (x, y);
-        state.colours = pointwise_colours(state.pixels.clone(), state.wavelength, x, y);
+        state.colours_dirty = true;
+        state.share_due = Some(now() + SHARE_DELAY);
+    });
+}
+
+// write a moved center into the hash once it's been still long enough, or
+// straight away given an infinite `now`. returns whether it was written
+pub fn share_center(now: f64) -> bool {
+    let due = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let due = state.share_due.is_some_and(|due| now >= due);
+        if due { state.share_due = None; }
+        due
     });
+    if due { share::update(Demo::SinWave); }
+    due
 }
 
+// wavelength, center and resolution, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    STATE.with(|state| {
+        let state = state.borrow();
+        let mut params = Params::new();
+        params.set("wavelength", share::number(state.wavelength));
+        params.set("center", share::groups(&[vec![state.center.0, state.center.1]]));
+        params.set("res", state.grid.resolution().to_string());
+        params
+    })
+}
+
+pub(crate) fn load_params(params: &Params) {
+    if let Some(w) = params.number::<f32>("wavelength").filter(|w| w.is_finite()) { s_update_wavelength(w); }
+    if let Some([x, y]) = params.groups("center").and_then(|g| <[f32; 2]>::try_from(g.concat()).ok()) {
+        s_mouse_move(x, y);
+    }
+    if let Some(res) = params.number::<i32>("res").filter(|res| (MIN_RESOLUTION..=MAX_RESOLUTION).contains(res)) { s_update_resolution(res); }
+}
+
+// evaluate the wave per fragment on the gpu, or per vertex on the cpu
 #[wasm_bindgen]
-pub fn init_gl(canvas_id: &str) -> WebGlRenderingContext {
+pub fn s_set_gpu(enabled: bool) {
+    STATE.with(|state| state.borrow_mut().gpu = enabled);
+}
 
-    // create gl context and shader program
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
-    
-    let vertex_shader_source =
-        "
-        attribute vec2 coordinates;
-        attribute vec3 colour;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_Position = vec4(coordinates, 0.0, 1.0);
-            out_colour = colour;
-        }
-        ";
-    let fragment_shader_source = 
-        "
-        precision mediump float;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_FragColor = vec4(out_colour, 1.0);
-        }
-        ";
+// snap the gpu wave to the same grid as the cpu path
+#[wasm_bindgen]
+pub fn s_set_quantised(enabled: bool) {
+    STATE.with(|state| state.borrow_mut().quantised = enabled);
+}
 
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
+// restart the colour source from a fixed seed so the wave can be reproduced
+pub fn reseed(seed: u64) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.rng = StdRng::seed_from_u64(seed);
+        state.colours_dirty = true;
+    });
+}
 
-    // spawn the ARRAY_BUFFER for the vertices to use each frame
-    gl.bind_buffer(
-        WebGlRenderingContext::ARRAY_BUFFER, 
-        Some(&gl.create_buffer().unwrap())
-    );
+// create the gl context for the canvas and keep it in the state
+#[wasm_bindgen]
+pub fn init_gl(canvas_id: &str) -> Result<(), ViewerError> {
 
-    // specify how the coordinates attribute should read from the vertex buffer
-    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates") as u32;
-    gl.vertex_attrib_pointer_with_i32(
-        coordinates_location, 
-        2, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        0
-    );
-    gl.enable_vertex_attrib_array(coordinates_location);
+    // pick up a shared state when the demo starts
+    share::restore(Demo::SinWave);
 
-    // specify how the colour attribute should read from from the vertex buffer
-    let colour_location = gl.get_attrib_location(&shader_program, "colour") as u32; 
-    gl.vertex_attrib_pointer_with_i32(
-        colour_location, 
-        3, 
-        WebGlRenderingContext::FLOAT, 
-        false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        2 * std::mem::size_of::<f32>() as i32,
-    );
-    gl.enable_vertex_attrib_array(colour_location);
+    // create gl context and shader program
+    let renderer = WebGlRenderer::for_canvas(canvas_id)?;
+    STATE.with(|state| state.borrow_mut().renderer = Some(renderer));
+
+    Ok(())
 
-    gl
+}
+
+// follow the mouse, or a finger or pen dragged across the canvas, and draw the
+// wave every frame until the handle stops it
+#[wasm_bindgen]
+pub fn sin_wave_init(canvas_id: &str) -> Result<AnimationLoop, ViewerError> {
+    init_gl(canvas_id)?;
+
+    let mut listeners = Listeners::new();
+    input::listen(&mut listeners, canvas_id, |event: &PointerEvent| {
+        if !event.primary { return; }
+        if event.phase == Phase::Up {
+            share_center(f64::INFINITY);
+            return;
+        }
+        let [x, y] = event.pointer.position;
+        s_mouse_move((x + 1.0) / 2.0, (1.0 - y) / 2.0);
+    })?;
 
+    let id = canvas_id.to_owned();
+    AnimationLoop::for_canvas(canvas_id, listeners, move || sin_draw(&id))
 }
 
 #[wasm_bindgen]
 pub fn mouse_move(_x: f32, _y: f32) {
     STATE.with(|state| {
         let mut state = state.borrow_mut();
-        state.pixels = make_pixels(state.resolution);
-        state.colours = pointwise_colours(state.pixels.clone(), state.wavelength, state.center.0, state.center.1);
+        state.grid = GridMesh::new(state.grid.resolution());
+        state.colours_dirty = true;
     });
 }
 
 #[wasm_bindgen]
-pub fn sin_draw(_c_id: &str) {
+pub fn sin_draw(canvas_id: &str) -> Result<(), ViewerError> {
+
+    // lazily create the context on the first draw
+    if STATE.with(|state| state.borrow().renderer.is_none()) {
+        init_gl(canvas_id)?;
+    }
+
+    let mut renderer = STATE.with(|state| state.borrow().renderer.clone()).unwrap();
+    share_center(now());
+
+    // fall back to the cpu path for good if the wave shader can't be used
+    if STATE.with(|state| state.borrow().gpu) {
+        match render_gpu(&renderer) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                web_sys::console::warn_1(&e.into());
+                s_set_gpu(false);
+            }
+        }
+    }
+
+    render(&mut renderer)
+}
 
+// draw the wave at its current center, one colour per grid vertex
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
     STATE.with(|state: &RefCell<STATE>| {
 
-        // get a mutable version state 
-        let state: std::cell::RefMut<STATE> = state.borrow_mut();
+        // get the state for this program
+        let mut state: std::cell::RefMut<STATE> = state.borrow_mut();
+        if state.colours_dirty {
+            state.recolour();
+        }
 
-        // zip the two lists together
-        let data: Vec<f32> = state.pixels
-            .chunks(2)
-            .zip(state.colours.iter())
-            .flat_map(|(v, c)| vec![v[0], v[1], c.red, c.green, c.blue])
-            .collect::<Vec<f32>>();
+        // one vertex per corner with its colour
+        let data = state.grid.shared_vertices(|i, _| state.colours[i]);
 
         // draw on the screen
-        state.gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        renderer.clear([0.0, 0.0, 0.0, 0.0]);
 
-        // draw shape
-        state.gl.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ARRAY_BUFFER,
-            &(unsafe { js_sys::Float32Array::view(&data).into() }),
-            WebGlRenderingContext::STATIC_DRAW,
-        );
-        state.gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, data.len() as i32 / 5);
+        // draw shape, each corner is shared by the squares around it
+        renderer.upload(&data)?;
+        renderer.draw_indexed(Primitive::Triangles, state.grid.indices())
+    })
+}
+
+// the grid coordinates of the corner nearest a point in clip space
+fn nearest_corner(position: [f32; 2], resolution: i32) -> [i32; 2] {
+    let resolution = resolution as f32;
+    position.map(|v| ((v + 1.0) * resolution / 2.0 + 0.5).floor() as i32)
+}
+
+// the brightness of the wave at a point in clip space, as the fragment shader sees it
+fn wave(position: [f32; 2], wavelength: f32, center: (f32, f32), resolution: Option<i32>) -> f32 {
+    let [mut x, mut y] = position;
+
+    // snap to the nearest corner of the cpu grid
+    if let Some(resolution) = resolution {
+        [x, y] = nearest_corner(position, resolution).map(|v| v as f32 / resolution as f32 * 2.0 - 1.0);
+    }
+
+    let dist = ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt();
+    (1.0 + (wavelength * dist).cos()) / 2.0
+}
 
+// the red and green of the corner nearest a point, as the fragment shader reads
+// them from the tint texture
+fn tint(position: [f32; 2], grid: &GridMesh, colours: &[Srgb]) -> [f32; 2] {
+    let side = grid.resolution() + 1;
+    let [x, y] = nearest_corner(position, grid.resolution()).map(|v| v.clamp(0, side - 1));
+    let colour = colours[(x * side + y) as usize];
+    [colour.red, colour.green]
+}
+
+// the gpu path evaluated on the cpu, for comparing against `render`
+pub fn render_fragments(renderer: &mut CpuRenderer) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.colours_dirty {
+            state.recolour();
+        }
+        let resolution = state.quantised.then_some(state.grid.resolution());
+        renderer.shade(|position| {
+            let [red, green] = tint(position, &state.grid, &state.colours);
+            [red, green, wave(position, state.wavelength, state.center, resolution)]
+        });
     });
 }
+
+// the tint texture, made on first use and filled again whenever the colours change
+fn upload_tint(gl: &WebGlRenderingContext, state: &mut STATE) -> Result<(), ViewerError> {
+    if state.tint.is_none() {
+        let texture = gl.create_texture().ok_or_else(|| ViewerError::ContextUnavailable("sin wave tint texture".into()))?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+
+        // one texel per corner exactly, at any grid size
+        for (parameter, value) in [
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+        ] {
+            gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+        state.tint = Some(texture);
+        state.tint_dirty = true;
+    }
+
+    gl.active_texture(WebGlRenderingContext::TEXTURE0);
+    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, state.tint.as_ref());
+    if !state.tint_dirty { return Ok(()); }
+
+    let texels: Vec<u8> = state.colours.iter()
+        .flat_map(|colour| {
+            let colour = colour.into_format::<u8>();
+            [colour.red, colour.green, 0]
+        })
+        .collect();
+    let side = state.grid.resolution() + 1;
+
+    // rows of three bytes a texel aren't four byte aligned
+    gl.pixel_storei(WebGlRenderingContext::UNPACK_ALIGNMENT, 1);
+    gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        WebGlRenderingContext::RGB as i32,
+        side,
+        side,
+        0,
+        WebGlRenderingContext::RGB,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        Some(&texels),
+    ).map_err(|_| ViewerError::ContextUnavailable("sin wave tint texture".into()))?;
+    state.tint_dirty = false;
+    Ok(())
+}
+
+fn render_gpu(renderer: &WebGlRenderer) -> Result<(), ViewerError> {
+
+    let gl = renderer.gl();
+    let shader_program = cached_program(gl, WAVE_VERTEX_SHADER_SOURCE, WAVE_FRAGMENT_SHADER_SOURCE)?;
+    let quad_buffer = static_buffer(gl, "sin_wave_quad", || FULL_SCREEN_QUAD.to_vec())?;
+
+    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad_buffer));
+    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates") as u32;
+    gl.vertex_attrib_pointer_with_i32(coordinates_location, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+    gl.enable_vertex_attrib_array(coordinates_location);
+
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.colours_dirty {
+            state.recolour();
+        }
+        upload_tint(gl, &mut state)?;
+
+        let uniform = |name: &str| gl.get_uniform_location(&shader_program, name);
+        gl.uniform1f(uniform("wavelength").as_ref(), state.wavelength);
+        gl.uniform2f(uniform("center").as_ref(), state.center.0, state.center.1);
+        gl.uniform1f(uniform("resolution").as_ref(), state.grid.resolution() as f32);
+        gl.uniform1i(uniform("quantised").as_ref(), state.quantised as i32);
+        gl.uniform1i(uniform("tint").as_ref(), 0);
+        Ok::<(), ViewerError>(())
+    })?;
+
+    // draw on the screen, at whatever size the canvas is now
+    gl.viewport(0, 0, gl.drawing_buffer_width(), gl.drawing_buffer_height());
+    gl.clear_color(0.0, 0.0, 0.0, 0.0);
+    gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+
+    Ok(())
+}
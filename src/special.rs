@@ -1,16 +1,27 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlRenderingContext, WebGlProgram};
+use web_sys::WebGlRenderingContext;
 use palette::{Hsv, Srgb, FromColor};
 use std::cell::RefCell;
-extern crate js_sys;
 
 use std::f64::consts::PI;
-use crate::utils::{init_webgl_context, link_shaders};
+use crate::animation::AnimationLoop;
+use crate::clock::Clock;
+use crate::error::ViewerError;
+use crate::listeners::Listeners;
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::share::{self, Demo, Params};
+
+// the sides the slider offers, a link asking for anything else is ignored
+const MIN_SIDES: i32 = 3;
+const MAX_SIDES: i32 = 20;
 
 // define the state
+#[allow(clippy::upper_case_acronyms)]
 struct STATE {
     vertices: Vec<f32>,
-    time: i32,
+    // in ticks, so the speeds below are per frame at 60 frames a second
+    time: f64,
+    clock: Clock,
     rotate_speed: i32,
     colour_speed: i32,
 }
@@ -19,7 +30,8 @@ struct STATE {
 thread_local! {
     static STATE: RefCell<STATE> = RefCell::new(STATE {
         vertices: get_coords_of_ngon(8),
-        time: 0,
+        time: 0.0,
+        clock: Clock::new(),
         rotate_speed: 50,
         colour_speed: 50,
     });
@@ -32,6 +44,7 @@ pub fn update_sides(n: i32) {
         let mut state = state.borrow_mut();
         state.vertices = get_coords_of_ngon(n);
     });
+    share::update(Demo::Special);
 }
 
 // update the speed of the rotation
@@ -41,6 +54,7 @@ pub fn update_rotation_speed(s: i32) {
         let mut state = state.borrow_mut();
         state.rotate_speed = s;
     });
+    share::update(Demo::Special);
 }
 
 // update the speed of the rainbow chase
@@ -50,105 +64,109 @@ pub fn update_colour_speed(s: i32) {
         let mut state = state.borrow_mut();
         state.colour_speed = s;
     });
+    share::update(Demo::Special);
+}
+
+// side count and speeds, for sharing through the url
+pub(crate) fn share_params() -> Params {
+    STATE.with(|state| {
+        let state = state.borrow();
+        let mut params = Params::new();
+        params.set("sides", (state.vertices.len() / 2).to_string());
+        params.set("rotate", state.rotate_speed.to_string());
+        params.set("colour", state.colour_speed.to_string());
+        params
+    })
+}
+
+pub(crate) fn load_params(params: &Params) {
+    if let Some(n) = params.number::<i32>("sides").filter(|n| (MIN_SIDES..=MAX_SIDES).contains(n)) { update_sides(n); }
+    if let Some(s) = params.number("rotate") { update_rotation_speed(s); }
+    if let Some(s) = params.number("colour") { update_colour_speed(s); }
 }
 
 // generate a color based on time
-fn rainbow_chase(time: i32) -> Vec<f32> {
-    let hsv_color = Hsv::new(time as f64, 1.0, 1.0);
+fn rainbow_chase(hue: f64) -> Vec<f32> {
+    let hsv_color = Hsv::new(hue, 1.0, 1.0);
     let color: Srgb = Srgb::from_color(hsv_color).into();
     vec![color.red, color.green, color.blue, 1.0]
 }
 
 // generate the coordinates of an n-gon using roots of unity/ polar coords
 fn get_coords_of_ngon(n: i32) -> Vec<f32> {
-    (0..n).map(|k| {
+    (0..n).flat_map(|k| {
         let theta = 2.0 * PI * ((k) as f64) / (n as f64);
         vec!(theta.cos() as f32, theta.sin() as f32)
-    }).into_iter().flat_map(|v| v).collect()
+    }).collect()
 }
 
 fn rotate_2d_coords(coords: Vec<f32>, angle: f32) -> Vec<f32> {
     coords.chunks(2)
-        .map(|c| {
+        .flat_map(|c| {
             vec![
                 c[0] * angle.cos() - c[1] * angle.sin(),
                 c[0] * angle.sin() + c[1] * angle.cos(), 
             ]
-        }).flatten().collect()
+        }).collect()
 }
 
 
 
 // draw the state to the screen given 
 #[wasm_bindgen]
-pub fn draw(canvas_id: &str) -> Result<WebGlRenderingContext, JsValue> {
+pub fn draw(canvas_id: &str) -> Result<WebGlRenderingContext, ViewerError> {
 
     // create gl context and shader program
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
-    
-    let vertex_shader_source =
-        "
-        attribute vec2 coordinates;
-
-        void main(void) {
-            gl_Position = vec4(coordinates, 1.0, 1.0);
-        }
-        ";
-    let fragment_shader_source = 
-        "
-        precision mediump float;
-
-        uniform vec4 fragColor;
-
-        void main(void) {
-            gl_FragColor = fragColor;
-        }
-        ";
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
+    let mut renderer = WebGlRenderer::for_canvas(canvas_id)?;
 
+    // the same speed whatever the frame rate
     STATE.with(|state| {
-
-        // get the state for this program
         let mut state = state.borrow_mut();
-        state.time += 1; 
+        state.clock.tick();
+        state.time += state.clock.delta_ticks();
+    });
+    render(&mut renderer)?;
 
-        let rotated_verts = rotate_2d_coords(
-            state.vertices.clone(), 
-            state.time as f32 * state.rotate_speed as f32 * 0.001
-        );
+    Ok(renderer.gl().clone())
+}
 
-        // bind the vertices to the shader program
-        setup_vertices(&gl, &rotated_verts, &shader_program);
+// draw the demo every frame until the handle stops it
+#[wasm_bindgen]
+pub fn special_init(canvas_id: &str) -> Result<AnimationLoop, ViewerError> {
 
-        // set fragment shader to colour the right color
-        let color = rainbow_chase(state.time * state.colour_speed / 50);
-        let color_location = gl.get_uniform_location(&shader_program, "fragColor").unwrap();
-        gl.uniform4fv_with_f32_array(Some(&color_location), &color);
-        
-        // draw on the screen
-        gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
-        let vertices_count = (state.vertices.len() / 2) as i32;
-        gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, vertices_count);
-    });
+    // pick up a shared state when the demo starts
+    share::restore(Demo::Special);
 
-    Ok(gl)
+    let id = canvas_id.to_owned();
+    AnimationLoop::for_canvas(canvas_id, Listeners::new(), move || draw(&id).map(|_| ()))
 }
 
-// bind the vertices to the shader program
-fn setup_vertices(gl: &WebGlRenderingContext, vertices: &[f32], shader_program: &WebGlProgram) {
+// draw the n-gon at the current time
+pub fn render(renderer: &mut impl Renderer) -> Result<(), ViewerError> {
 
-    let vertices_array = unsafe { js_sys::Float32Array::view(&vertices) };
+    // keep the n-gon round on a canvas that isn't square
+    let viewport = renderer.viewport();
 
-    let vertex_buffer = gl.create_buffer().unwrap();
-    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
-    gl.buffer_data_with_array_buffer_view(
-        WebGlRenderingContext::ARRAY_BUFFER,
-        &vertices_array,
-        WebGlRenderingContext::STATIC_DRAW,
-    );
+    STATE.with(|state| {
 
-    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates");
-    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
-    gl.vertex_attrib_pointer_with_i32(coordinates_location as u32, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
-    gl.enable_vertex_attrib_array(coordinates_location as u32);
-}
\ No newline at end of file
+        // get the state for this program
+        let state = state.borrow();
+
+        let rotated_verts = rotate_2d_coords(
+            state.vertices.clone(), 
+            (state.time * state.rotate_speed as f64 * 0.001) as f32
+        );
+
+        // colour every vertex the right color
+        let color = rainbow_chase(state.time * state.colour_speed as f64 / 50.0);
+        let data: Vec<f32> = rotated_verts
+            .chunks(2)
+            .map(|v| viewport.world_to_clip([v[0], v[1]]))
+            .flat_map(|v| vec![v[0], v[1], color[0], color[1], color[2]])
+            .collect();
+
+        // draw on the screen
+        renderer.clear([0.0, 0.0, 0.0, 0.0]);
+        renderer.draw_vertices(Primitive::TriangleFan, &data)
+    })
+}
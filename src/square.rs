@@ -1,59 +1,38 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlRenderingContext, WebGlProgram};
-extern crate js_sys;
+use web_sys::WebGlRenderingContext;
 
-use crate::utils::{init_webgl_context, link_shaders, setup_vertices};
+use crate::error::ViewerError;
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::utils::rgb;
 
 #[wasm_bindgen]
 pub fn draw_square(
     canvas_id: &str,
     selected_color: Option<Vec<f32>>,
-) -> Result<WebGlRenderingContext, JsValue> {
+) -> Result<WebGlRenderingContext, ViewerError> {
 
     // create gl context and shader program
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
-    let vertex_shader_source = "
-        attribute vec3 coordinates;
+    let mut renderer = WebGlRenderer::for_canvas(canvas_id)?;
 
-        void main(void) {
-            gl_Position = vec4(coordinates, 1.0);
-        }
-        ";
-    let fragment_shader_source = "
-        precision mediump float;
+    // set the color to shade these verticies
+    let color = selected_color.unwrap_or(vec![1.0, 0.0, 0.0, 1.0]);
+    render(&mut renderer, &color)?;
 
-        uniform vec4 fragColor;
+    Ok(renderer.gl().clone())
+}
 
-        void main(void) {
-            gl_FragColor = fragColor;
-        }
-        ";
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
+// draw a square in the middle of the canvas in a single colour
+pub fn render(renderer: &mut impl Renderer, color: &[f32]) -> Result<(), ViewerError> {
+    let [r, g, b] = rgb(color)?;
 
     // define the vertices of the square
-    let vertices: [f32; 12] = [
-        -0.5, -0.5, 0.0, // bottom left
-        0.5, -0.5, 0.0, // bottom right
-        -0.5, 0.5, 0.0, // top left
-        0.5, 0.5, 0.0, // top right
+    let vertices: [f32; 20] = [
+        -0.5, -0.5, r, g, b, // bottom left
+        0.5, -0.5, r, g, b, // bottom right
+        -0.5, 0.5, r, g, b, // top left
+        0.5, 0.5, r, g, b, // top right
     ];
 
-    // bind the verticies to the buffer 
-    setup_vertices(&gl, &vertices, &shader_program);
-
-    // set the color to shade these verticies
-    let color = selected_color.unwrap_or(vec![1.0, 0.0, 0.0, 1.0]);
-    let color_location = gl
-        .get_uniform_location(&shader_program, "fragColor")
-        .unwrap();
-    gl.uniform4fv_with_f32_array(Some(&color_location), &color);
-    
-    gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
-    gl.draw_arrays(
-        WebGlRenderingContext::TRIANGLE_STRIP,
-        0,
-        (vertices.len() / 3) as i32,
-    );
-
-    Ok(gl)
+    renderer.clear([0.0, 0.0, 0.0, 0.0]);
+    renderer.draw_vertices(Primitive::TriangleStrip, &vertices)
 }
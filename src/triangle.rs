@@ -1,53 +1,32 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlRenderingContext, WebGlProgram};
-extern crate js_sys;
+use web_sys::WebGlRenderingContext;
 
-use crate::utils::{init_webgl_context, link_shaders, setup_vertices};
+use crate::error::ViewerError;
+use crate::renderer::{Primitive, Renderer, WebGlRenderer};
+use crate::utils::rgb;
 
 #[wasm_bindgen]
 pub fn draw_triangle(
     canvas_id: &str,
     selected_color: Option<Vec<f32>>,
-) -> Result<WebGlRenderingContext, JsValue> {
-    let gl: WebGlRenderingContext = init_webgl_context(canvas_id).unwrap();
+) -> Result<WebGlRenderingContext, ViewerError> {
+    let mut renderer = WebGlRenderer::for_canvas(canvas_id)?;
 
-    let vertex_shader_source = "
-        attribute vec3 coordinates;
-
-        void main(void) {
-            gl_Position = vec4(coordinates, 1.0);
-        }
-        ";
-    let fragment_shader_source = "
-        precision mediump float;
+    let color = selected_color.unwrap_or(vec![1.0, 0.0, 0.0, 1.0]);
+    render(&mut renderer, &color)?;
 
-        uniform vec4 fragColor;
+    Ok(renderer.gl().clone())
+}
 
-        void main(void) {
-            gl_FragColor = fragColor;
-        }
-        ";
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source);
+// draw a triangle filling the canvas in a single colour
+pub fn render(renderer: &mut impl Renderer, color: &[f32]) -> Result<(), ViewerError> {
+    let [r, g, b] = rgb(color)?;
 
-    let vertices: [f32; 9] = [
-        0.0, 1.0, 0.0, // top
-        -1.0, -1.0, 0.0, // bottom left
-        1.0, -1.0, 0.0, // bottom right
+    let vertices: [f32; 15] = [
+        0.0, 1.0, r, g, b, // top
+        -1.0, -1.0, r, g, b, // bottom left
+        1.0, -1.0, r, g, b, // bottom right
     ];
 
-    setup_vertices(&gl, &vertices, &shader_program);
-
-    let color = selected_color.unwrap_or(vec![1.0, 0.0, 0.0, 1.0]);
-    let color_location = gl
-        .get_uniform_location(&shader_program, "fragColor")
-        .unwrap();
-    gl.uniform4fv_with_f32_array(Some(&color_location), &color);
-
-    gl.draw_arrays(
-        WebGlRenderingContext::TRIANGLES,
-        0,
-        (vertices.len() / 3) as i32,
-    );
-
-    Ok(gl)
+    renderer.draw_vertices(Primitive::Triangles, &vertices)
 }
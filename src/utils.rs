@@ -1,91 +1,294 @@
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{WebGlRenderingContext, WebGlShader, WebGlProgram};
+use web_sys::{
+    AngleInstancedArrays, HtmlCanvasElement, WebGl2RenderingContext, WebGlBuffer, WebGlRenderingContext, WebGlShader, WebGlProgram,
+};
+
+use crate::error::{ShaderStage, ViewerError};
+use crate::viewport::fit_canvas;
 
-pub fn default_gl() -> WebGlRenderingContext{
+// programs and buffers that have already been created for one context
+pub struct GlCache<P, B> {
+    programs: HashMap<u64, P>,
+    buffers: HashMap<&'static str, B>,
+}
+
+impl<P: Clone, B: Clone> Default for GlCache<P, B> {
+    fn default() -> Self {
+        GlCache { programs: HashMap::new(), buffers: HashMap::new() }
+    }
+}
 
-    // create shader program
-    let vertex_shader_source =
-        "
-        attribute vec2 coordinates;
-        attribute vec3 colour;
-        varying vec3 out_colour;
+impl<P: Clone, B: Clone> GlCache<P, B> {
 
-        void main(void) {
-            gl_Position = vec4(coordinates, 0.0, 1.0);
-            out_colour = colour;
+    // return the program built from these sources, linking it on first use
+    pub fn program<E>(
+        &mut self,
+        vertex_shader_source: &str,
+        fragment_shader_source: &str,
+        link: impl FnOnce() -> Result<P, E>,
+    ) -> Result<P, E> {
+        let key = source_hash(vertex_shader_source, fragment_shader_source);
+        if let Some(program) = self.programs.get(&key) {
+            return Ok(program.clone());
         }
-        ";
-    let fragment_shader_source = 
-        "
-        precision mediump float;
-        varying vec3 out_colour;
-
-        void main(void) {
-            gl_FragColor = vec4(out_colour, 1.0);
+        let program = link()?;
+        PROGRAMS_LINKED.with(|count| count.set(count.get() + 1));
+        self.programs.insert(key, program.clone());
+        Ok(program)
+    }
+
+    // return the named buffer, creating it on first use
+    pub fn buffer<E>(
+        &mut self,
+        name: &'static str,
+        create: impl FnOnce() -> Result<B, E>,
+    ) -> Result<B, E> {
+        if let Some(buffer) = self.buffers.get(name) {
+            return Ok(buffer.clone());
         }
-        ";
+        let buffer = create()?;
+        BUFFERS_CREATED.with(|count| count.set(count.get() + 1));
+        self.buffers.insert(name, buffer.clone());
+        Ok(buffer)
+    }
+}
 
+fn source_hash(vertex_shader_source: &str, fragment_shader_source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    vertex_shader_source.hash(&mut hasher);
+    fragment_shader_source.hash(&mut hasher);
+    hasher.finish()
+}
 
-    let gl = init_webgl_context("user_input").unwrap();
-        
-    // spawn the ARRAY_BUFFER for the vertices to use each frame
-    let shader_program: WebGlProgram = link_shaders(&gl, vertex_shader_source, fragment_shader_source); 
-    gl.bind_buffer(
-        WebGlRenderingContext::ARRAY_BUFFER, 
-        Some(&gl.create_buffer().unwrap())
-    );
+thread_local! {
+    static PROGRAMS_LINKED: Cell<usize> = const { Cell::new(0) };
+    static BUFFERS_CREATED: Cell<usize> = const { Cell::new(0) };
 
-    // specify how the coordinates attribute should read from the vertex buffer
-    let coordinate_location = gl.get_attrib_location(&shader_program, "coordinates") as u32;
-    gl.vertex_attrib_pointer_with_i32(
-        coordinate_location, 2, WebGlRenderingContext::FLOAT, 
-        false, 5 * std::mem::size_of::<f32>() as i32, 0
-    );
-    gl.enable_vertex_attrib_array(coordinate_location);
-
-    // specify how the colour attribute should read from from the vertex buffer
-    let colour_location = gl.get_attrib_location(&shader_program, "colour") as u32; 
-    gl.vertex_attrib_pointer_with_i32(
-        colour_location, 3, WebGlRenderingContext::FLOAT, false, 
-        5 * std::mem::size_of::<f32>() as i32,
-        2 * std::mem::size_of::<f32>() as i32,
+    // one cache per context, looked up by js identity
+    static GL_CACHES: RefCell<Vec<(WebGlRenderingContext, GlCache<WebGlProgram, WebGlBuffer>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+// number of shader programs linked through the cache so far
+#[wasm_bindgen]
+pub fn programs_linked() -> usize {
+    PROGRAMS_LINKED.with(|count| count.get())
+}
+
+// number of buffers created through the pool so far
+#[wasm_bindgen]
+pub fn buffers_created() -> usize {
+    BUFFERS_CREATED.with(|count| count.get())
+}
+
+fn with_gl_cache<T>(
+    gl: &WebGlRenderingContext,
+    f: impl FnOnce(&mut GlCache<WebGlProgram, WebGlBuffer>) -> T,
+) -> T {
+    GL_CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        let index = match caches.iter().position(|(context, _)| context == gl) {
+            Some(index) => index,
+            None => {
+                caches.push((gl.clone(), GlCache::default()));
+                caches.len() - 1
+            }
+        };
+        f(&mut caches[index].1)
+    })
+}
+
+// let go of the programs and buffers cached for the context on this canvas, once
+// its demo has been taken off the page
+pub fn evict_gl_cache(canvas: &HtmlCanvasElement) {
+    let canvas: &JsValue = canvas.as_ref();
+    GL_CACHES.with(|caches| {
+        caches.borrow_mut().retain(|(context, _)| context.canvas().map(JsValue::from).as_ref() != Some(canvas));
+    });
+}
+
+// link the shaders once per context and make the program active
+pub fn cached_program(
+    gl: &WebGlRenderingContext,
+    vertex_shader_source: &str,
+    fragment_shader_source: &str,
+) -> Result<WebGlProgram, ViewerError> {
+    let program = with_gl_cache(gl, |cache| {
+        cache.program(vertex_shader_source, fragment_shader_source, || {
+            link_shaders(gl, vertex_shader_source, fragment_shader_source)
+        })
+    })?;
+    gl.use_program(Some(&program));
+    Ok(program)
+}
+
+// a buffer that lives for as long as the context, reused across frames
+pub fn pooled_buffer(gl: &WebGlRenderingContext, name: &'static str) -> Result<WebGlBuffer, ViewerError> {
+    with_gl_cache(gl, |cache| cache.buffer(name, || create_buffer(gl)))
+}
+
+// a buffer filled once with data that never changes, shared across frames. the
+// data is only built when the buffer is first made
+pub fn static_buffer(
+    gl: &WebGlRenderingContext,
+    name: &'static str,
+    data: impl FnOnce() -> Vec<f32>,
+) -> Result<WebGlBuffer, ViewerError> {
+    with_gl_cache(gl, |cache| cache.buffer(name, || {
+        let buffer = create_buffer(gl)?;
+        upload_buffer(gl, &buffer, &data());
+        Ok(buffer)
+    }))
+}
+
+// replace what's in a buffer
+pub fn upload_buffer(gl: &WebGlRenderingContext, buffer: &WebGlBuffer, data: &[f32]) {
+    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &(unsafe { js_sys::Float32Array::view(data).into() }),
+        WebGlRenderingContext::STATIC_DRAW,
     );
-    gl.enable_vertex_attrib_array(colour_location);
-    gl
 }
 
-pub fn init_webgl_context(canvas_id: &str) -> Result<WebGlRenderingContext, JsValue> {
+// the rgb of a colour from js, given as [r, g, b] or [r, g, b, a]. shapes are drawn
+// opaque, so an alpha other than 1 is an error rather than quietly dropped
+pub fn rgb(color: &[f32]) -> Result<[f32; 3], ViewerError> {
+    match *color {
+        [r, g, b] | [r, g, b, 1.0] => Ok([r, g, b]),
+        [_, _, _, a] => Err(ViewerError::InvalidColor(format!("alpha must be 1, got {}", a))),
+        _ => Err(ViewerError::InvalidColor(format!("expected 3 or 4 components, got {}", color.len()))),
+    }
+}
+
+// look up an element by id and cast it to the expected type
+pub fn get_element<T: JsCast>(id: &str, expected: &'static str) -> Result<T, ViewerError> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| ViewerError::ContextUnavailable("document".into()))?;
+
+    document
+        .get_element_by_id(id)
+        .ok_or_else(|| ViewerError::MissingElement(id.into()))?
+        .dyn_into::<T>()
+        .map_err(|_| ViewerError::WrongElementType { id: id.into(), expected })
+}
+
+pub fn get_canvas(canvas_id: &str) -> Result<web_sys::HtmlCanvasElement, ViewerError> {
+    get_element(canvas_id, "canvas").map_err(|e| match e {
+        ViewerError::MissingElement(id) => ViewerError::MissingCanvas(id),
+        e => e,
+    })
+}
+
+// which flavour of context a canvas ended up with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlVersion {
+    WebGl1,
+    WebGl2,
+}
+
+impl GlVersion {
+    pub fn of(gl: &WebGlRenderingContext) -> GlVersion {
+        if gl.is_instance_of::<WebGl2RenderingContext>() {
+            GlVersion::WebGl2
+        } else {
+            GlVersion::WebGl1
+        }
+    }
+}
+
+// the webgl2 api of a context, when the browser gave us one
+pub fn as_webgl2(gl: &WebGlRenderingContext) -> Option<&WebGl2RenderingContext> {
+    gl.dyn_ref::<WebGl2RenderingContext>()
+}
+
+// instanced drawing, from webgl2 or the ANGLE_instanced_arrays extension
+pub enum Instancing {
+    WebGl2(WebGl2RenderingContext),
+    Angle(AngleInstancedArrays),
+}
+
+impl Instancing {
+    pub fn for_context(gl: &WebGlRenderingContext) -> Option<Instancing> {
+        if let Some(gl2) = as_webgl2(gl) {
+            return Some(Instancing::WebGl2(gl2.clone()));
+        }
+        gl.get_extension("ANGLE_instanced_arrays")
+            .ok()
+            .flatten()
+            .map(|extension| Instancing::Angle(extension.unchecked_into()))
+    }
+
+    // advance the attribute once per `divisor` instances instead of once per vertex
+    pub fn vertex_attrib_divisor(&self, location: u32, divisor: u32) {
+        match self {
+            Instancing::WebGl2(gl2) => gl2.vertex_attrib_divisor(location, divisor),
+            Instancing::Angle(extension) => extension.vertex_attrib_divisor_angle(location, divisor),
+        }
+    }
+
+    pub fn draw_arrays_instanced(&self, mode: u32, first: i32, count: i32, instances: i32) {
+        match self {
+            Instancing::WebGl2(gl2) => gl2.draw_arrays_instanced(mode, first, count, instances),
+            Instancing::Angle(extension) => extension.draw_arrays_instanced_angle(mode, first, count, instances),
+        }
+    }
+}
+
+pub fn init_webgl_context(canvas_id: &str) -> Result<WebGlRenderingContext, ViewerError> {
     
     // get the canvas element from the DOM
-    let document = web_sys::window().unwrap().document().unwrap();
-    let canvas = document.get_element_by_id(canvas_id).unwrap();
-    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let canvas = get_canvas(canvas_id)?;
     
-    // spawn WebGL context
-    let gl: WebGlRenderingContext = canvas
-        .get_context("webgl")?
-        .unwrap()
-        .dyn_into::<WebGlRenderingContext>()
-        .unwrap();
+    // spawn WebGL context, preferring webgl2. a webgl2 context answers every
+    // webgl1 call, so both are handed around as a WebGlRenderingContext
+    let gl: WebGlRenderingContext = ["webgl2", "webgl"]
+        .iter()
+        .find_map(|kind| canvas.get_context(kind).ok().flatten())
+        .map(|context| context.unchecked_into::<WebGlRenderingContext>())
+        .ok_or_else(|| ViewerError::ContextUnavailable("webgl2 or webgl".into()))?;
+
+    // make the drawing buffer the size the canvas is on screen, in device pixels.
+    // animated demos keep it that way with a `ResizeWatch` on their loop
+    fit_canvas(&canvas);
 
     // set the size of the gl viewport to match the canvas
-    gl.viewport( 0, 0,
-        canvas.width().try_into().unwrap(),
-        canvas.height().try_into().unwrap(),
-    );
+    gl.viewport(0, 0, canvas.width() as i32, canvas.height() as i32);
 
     Ok(gl)
 }
 
+// 2 if the canvas got a webgl2 context, 1 if it fell back to webgl
+#[wasm_bindgen]
+pub fn webgl_version(canvas_id: &str) -> Result<u32, ViewerError> {
+    Ok(match GlVersion::of(&init_webgl_context(canvas_id)?) {
+        GlVersion::WebGl1 => 1,
+        GlVersion::WebGl2 => 2,
+    })
+}
+
+pub fn create_buffer(gl: &WebGlRenderingContext) -> Result<WebGlBuffer, ViewerError> {
+    gl.create_buffer()
+        .ok_or_else(|| ViewerError::ContextUnavailable("webgl buffer".into()))
+}
+
 // generic helper function to create a shader
 pub fn create_shader(
     gl: &WebGlRenderingContext,
     shader_type: u32,
     source: &str,
-) -> WebGlShader {
+) -> Result<WebGlShader, ViewerError> {
 
-    let shader = gl.create_shader(shader_type).unwrap();
+    let stage = ShaderStage::from_gl(shader_type);
+    let shader = gl.create_shader(shader_type)
+        .ok_or_else(|| ViewerError::ContextUnavailable(format!("webgl {} shader", stage)))?;
 
     gl.shader_source(&shader, source);
     gl.compile_shader(&shader);
@@ -95,11 +298,11 @@ pub fn create_shader(
         .as_bool()
         .unwrap_or(false)
     {
-        shader
+        Ok(shader)
     } else {
-        let error_message = gl.get_shader_info_log(&shader)
+        let log = gl.get_shader_info_log(&shader)
             .unwrap_or_else(|| "Unknown error creating shader".into());
-        panic!("Error compiling shader: {}", error_message);
+        Err(ViewerError::ShaderCompile { stage, log })
     }
 }
 
@@ -108,18 +311,19 @@ pub fn link_shaders(
     gl: &WebGlRenderingContext, 
     vertex_shader_source: &str, 
     fragment_shader_source: &str,
-) -> WebGlProgram {
+) -> Result<WebGlProgram, ViewerError> {
 
     let logging = false;
     if logging {web_sys::console::log_1(&"Compiling shaders...".into());}
 
-    let vertex_shader = create_shader(&gl, WebGlRenderingContext::VERTEX_SHADER, vertex_shader_source);
+    let vertex_shader = create_shader(gl, WebGlRenderingContext::VERTEX_SHADER, vertex_shader_source)?;
     if logging {web_sys::console::log_1(&"Successfully compiled vertex shader.".into());}
 
-    let fragment_shader = create_shader(&gl, WebGlRenderingContext::FRAGMENT_SHADER, fragment_shader_source);
+    let fragment_shader = create_shader(gl, WebGlRenderingContext::FRAGMENT_SHADER, fragment_shader_source)?;
     if logging {web_sys::console::log_1(&"Successfully compiled fragment shader.".into());}
 
-    let shader_program = gl.create_program().unwrap();
+    let shader_program = gl.create_program()
+        .ok_or_else(|| ViewerError::ContextUnavailable("webgl program".into()))?;
 
     gl.attach_shader(&shader_program, &vertex_shader);
     if logging {web_sys::console::log_1(&"Successfully attached vertex shader.".into());}
@@ -138,35 +342,11 @@ pub fn link_shaders(
     {
         // Set the shader program as active.
         gl.use_program(Some(&shader_program));
-        shader_program
+        Ok(shader_program)
     } else {
-        let error_message = gl.get_program_info_log(&shader_program)
+        let log = gl.get_program_info_log(&shader_program)
             .unwrap_or_else(|| "Unknown error linking program".into());
-        panic!("Error linking shader program: {}", error_message);
+        Err(ViewerError::Link(log))
     }
 }
 
-
-pub fn setup_vertices(gl: &WebGlRenderingContext, vertices: &[f32], shader_program: &WebGlProgram) {
-    let vertices_array = unsafe { js_sys::Float32Array::view(&vertices) };
-
-    let vertex_buffer = gl.create_buffer().unwrap();
-    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
-    gl.buffer_data_with_array_buffer_view(
-        WebGlRenderingContext::ARRAY_BUFFER,
-        &vertices_array,
-        WebGlRenderingContext::STATIC_DRAW,
-    );
-
-    let coordinates_location = gl.get_attrib_location(&shader_program, "coordinates");
-    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
-    gl.vertex_attrib_pointer_with_i32(
-        coordinates_location as u32,
-        3,
-        WebGlRenderingContext::FLOAT,
-        false,
-        0,
-        0,
-    );
-    gl.enable_vertex_attrib_array(coordinates_location as u32);
-}
\ No newline at end of file
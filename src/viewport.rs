@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlCanvasElement, MediaQueryList, ResizeObserver};
+
+use crate::error::ViewerError;
+
+// the size of what's being drawn into, in device pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn new(width: u32, height: u32) -> Viewport {
+        Viewport { width: width.max(1), height: height.max(1) }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    // how much to squash world x and y by so a world unit is the same number of pixels both ways
+    pub fn aspect_scale(&self) -> [f32; 2] {
+        let aspect = self.aspect();
+        if aspect >= 1.0 { [1.0 / aspect, 1.0] } else { [1.0, aspect] }
+    }
+
+    // the world is -1 to 1 along the canvas's shorter side, and further along the longer one
+    pub fn world_to_clip(&self, point: [f32; 2]) -> [f32; 2] {
+        let scale = self.aspect_scale();
+        [point[0] * scale[0], point[1] * scale[1]]
+    }
+
+    pub fn clip_to_world(&self, point: [f32; 2]) -> [f32; 2] {
+        let scale = self.aspect_scale();
+        [point[0] / scale[0], point[1] / scale[1]]
+    }
+}
+
+// the drawing buffer for an element this many css pixels across on this screen
+pub fn drawing_buffer_size(css_width: f64, css_height: f64, device_pixel_ratio: f64) -> (u32, u32) {
+    let device = |css: f64| (css * device_pixel_ratio).round().max(1.0) as u32;
+    (device(css_width), device(css_height))
+}
+
+// a mouse position relative to an element's bounding rect, -1 to 1 with y up
+pub fn client_to_clip(client: [f64; 2], rect_origin: [f64; 2], rect_size: [f64; 2]) -> [f32; 2] {
+    [
+        (-1.0 + 2.0 * (client[0] - rect_origin[0]) / rect_size[0]) as f32,
+        (1.0 - 2.0 * (client[1] - rect_origin[1]) / rect_size[1]) as f32,
+    ]
+}
+
+// size a canvas's drawing buffer to how big it is on the screen, returns true if it changed
+pub fn fit_canvas(canvas: &HtmlCanvasElement) -> bool {
+    let (css_width, css_height) = (canvas.client_width(), canvas.client_height());
+
+    // not laid out yet, keep the width and height it was given
+    if css_width <= 0 || css_height <= 0 {
+        return false;
+    }
+
+    let device_pixel_ratio = web_sys::window().map_or(1.0, |window| window.device_pixel_ratio());
+    let (width, height) = drawing_buffer_size(css_width as f64, css_height as f64, device_pixel_ratio);
+    if canvas.width() == width && canvas.height() == height {
+        return false;
+    }
+    canvas.set_width(width);
+    canvas.set_height(height);
+    true
+}
+
+// keeps a canvas's drawing buffer fitted to it as the page and the screen change,
+// while attached. an animation loop attaches it as it starts and detaches it as it
+// stops, and dropping it stops watching for good
+pub struct ResizeWatch {
+    canvas: HtmlCanvasElement,
+    observer: ResizeObserver,
+    // owns the rust side of the observer's callback
+    _on_resize: Closure<dyn FnMut()>,
+    // the media query matching the current device pixel ratio, and what listens to it
+    ratio: Rc<RefCell<Option<MediaQueryList>>>,
+    on_ratio: Closure<dyn FnMut()>,
+    attached: bool,
+}
+
+impl ResizeWatch {
+    pub fn new(canvas: &HtmlCanvasElement) -> Result<ResizeWatch, ViewerError> {
+
+        // the element changing size
+        let observed = canvas.clone();
+        let on_resize: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || { fit_canvas(&observed); }));
+        let observer = ResizeObserver::new(on_resize.as_ref().unchecked_ref())
+            .map_err(|_| ViewerError::ContextUnavailable("resize observer".into()))?;
+
+        // the device pixel ratio changing, from zooming or moving to another screen.
+        // the query only matches the old ratio, so it's swapped for one matching the new
+        let ratio = Rc::new(RefCell::new(None));
+        let this: Rc<RefCell<Option<Function>>> = Rc::new(RefCell::new(None));
+        let (observed, query, function) = (canvas.clone(), ratio.clone(), this.clone());
+        let on_ratio: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+            fit_canvas(&observed);
+            if let Some(function) = function.borrow().as_ref() {
+                if let Err(e) = watch_ratio(&query, function) {
+                    web_sys::console::error_1(&e.into());
+                }
+            }
+        }));
+        *this.borrow_mut() = Some(on_ratio.as_ref().unchecked_ref::<Function>().clone());
+
+        Ok(ResizeWatch { canvas: canvas.clone(), observer, _on_resize: on_resize, ratio, on_ratio, attached: false })
+    }
+
+    pub fn attached(&self) -> bool {
+        self.attached
+    }
+
+    // start watching, fitting the canvas to any change made while detached
+    pub fn attach(&mut self) -> Result<(), ViewerError> {
+        if self.attached { return Ok(()); }
+        fit_canvas(&self.canvas);
+        watch_ratio(&self.ratio, self.on_ratio.as_ref().unchecked_ref())?;
+        self.observer.observe(&self.canvas);
+        self.attached = true;
+        Ok(())
+    }
+
+    pub fn detach(&mut self) {
+        if !self.attached { return; }
+        self.observer.disconnect();
+        unwatch_ratio(&self.ratio, self.on_ratio.as_ref().unchecked_ref());
+        self.attached = false;
+    }
+}
+
+// listen for the screen's pixel ratio changing away from what it is now
+fn watch_ratio(ratio: &RefCell<Option<MediaQueryList>>, function: &Function) -> Result<(), ViewerError> {
+    unwatch_ratio(ratio, function);
+    let window = web_sys::window().ok_or_else(|| ViewerError::ContextUnavailable("window".into()))?;
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let Some(list) = window.match_media(&query).ok().flatten() else { return Ok(()); };
+    list.add_event_listener_with_callback("change", function)
+        .map_err(|_| ViewerError::Listener("change".into()))?;
+    *ratio.borrow_mut() = Some(list);
+    Ok(())
+}
+
+fn unwatch_ratio(ratio: &RefCell<Option<MediaQueryList>>, function: &Function) {
+    if let Some(list) = ratio.borrow_mut().take() {
+        let _ = list.remove_event_listener_with_callback("change", function);
+    }
+}
+
+impl fmt::Debug for ResizeWatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResizeWatch").field("attached", &self.attached).finish()
+    }
+}
+
+impl Drop for ResizeWatch {
+    fn drop(&mut self) {
+        self.detach();
+    }
+}
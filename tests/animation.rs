@@ -0,0 +1,89 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use webassembly_webgl_viewer::animation::AnimationLoop;
+use webassembly_webgl_viewer::error::ViewerError;
+use webassembly_webgl_viewer::listeners::Listeners;
+
+// a loop that counts its frames
+fn counting() -> (AnimationLoop, Rc<Cell<usize>>) {
+    let frames = Rc::new(Cell::new(0));
+    let counter = frames.clone();
+    let animation = AnimationLoop::new(Listeners::new(), move || {
+        counter.set(counter.get() + 1);
+        Ok(())
+    });
+    (animation, frames)
+}
+
+#[test]
+fn frames_only_draw_while_started() {
+    let (animation, frames) = counting();
+    assert!(!animation.running());
+    animation.step().unwrap();
+    assert_eq!(frames.get(), 0);
+
+    animation.start().unwrap();
+    animation.start().unwrap();
+    assert!(animation.running());
+    animation.step().unwrap();
+    animation.step().unwrap();
+    assert_eq!(frames.get(), 2);
+
+    animation.stop();
+    animation.stop();
+    animation.step().unwrap();
+    assert_eq!(frames.get(), 2);
+
+    // and picks up again after a stop
+    animation.start().unwrap();
+    animation.step().unwrap();
+    assert_eq!(frames.get(), 3);
+}
+
+#[test]
+fn a_failing_frame_stops_the_loop() {
+    let animation = AnimationLoop::new(Listeners::new(), || Err(ViewerError::ContextUnavailable("gl".into())));
+    animation.start().unwrap();
+
+    assert_eq!(animation.step(), Err(ViewerError::ContextUnavailable("gl".into())));
+    assert!(!animation.running());
+    assert_eq!(animation.step(), Ok(()));
+}
+
+#[test]
+fn freeing_the_handle_lets_go_of_the_frame() {
+    let (animation, frames) = counting();
+    animation.start().unwrap();
+    assert_eq!(Rc::strong_count(&frames), 2);
+
+    drop(animation);
+    assert_eq!(Rc::strong_count(&frames), 1);
+}
+
+#[test]
+fn listeners_start_off_the_page() {
+    let mut listeners = Listeners::new();
+    assert!(!listeners.attached());
+    listeners.attach().unwrap();
+    assert!(listeners.attached());
+    listeners.detach();
+    assert!(!listeners.attached());
+}
+
+#[test]
+fn taking_listeners_off_the_page_resets_what_they_follow() {
+    let resets = Rc::new(Cell::new(0));
+    let counter = resets.clone();
+    let mut listeners = Listeners::new();
+    listeners.on_detach(move || counter.set(counter.get() + 1));
+
+    // nothing to reset before they were ever attached
+    listeners.detach();
+    assert_eq!(resets.get(), 0);
+
+    listeners.attach().unwrap();
+    listeners.detach();
+    listeners.detach();
+    assert_eq!(resets.get(), 1);
+}
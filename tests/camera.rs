@@ -0,0 +1,130 @@
+use std::f32::consts::PI;
+
+use webassembly_webgl_viewer::camera::{Camera, OrbitAction, OrbitControls, Projection};
+use webassembly_webgl_viewer::math;
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+    a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3)
+}
+
+fn settle(orbit: &mut OrbitControls) {
+    for _frame in 0..200 {
+        orbit.update();
+    }
+}
+
+fn lights_camera() -> OrbitControls {
+    let camera = Camera::perspective(75.0_f32.to_radians(), 0.1, 1000.0).looking_at([75.0, 30.0, 0.0], [0.0; 3]);
+    let mut orbit = OrbitControls::new(camera);
+    orbit.max_polar_angle = PI / 2.0;
+    orbit
+}
+
+#[test]
+fn the_target_is_in_the_middle_of_the_view() {
+    let camera = Camera::perspective(1.0, 0.1, 100.0).looking_at([3.0, 4.0, 5.0], [1.0, 0.0, -1.0]);
+    let centre = math::transform_point(&camera.view_projection(1.5), camera.target);
+    assert!(centre[0].abs() < 1e-5 && centre[1].abs() < 1e-5, "{:?}", centre);
+
+    let camera = Camera::orthographic(10.0, 1.0, 21.0).looking_at([0.0, 0.0, 11.0], [0.0; 3]);
+    let corner = math::transform_point(&camera.view_projection(2.0), [10.0, 5.0, -10.0]);
+    assert!(close(corner, [1.0, 1.0, 1.0]), "{:?}", corner);
+}
+
+#[test]
+fn the_camera_starts_where_it_was_put() {
+    let mut orbit = lights_camera();
+    orbit.update();
+    assert!(close(orbit.camera().eye, [75.0, 30.0, 0.0]), "{:?}", orbit.camera().eye);
+
+    settle(&mut orbit);
+    assert!(!orbit.update());
+}
+
+#[test]
+fn rotating_eases_in_and_keeps_the_distance() {
+    let mut orbit = lights_camera();
+    let distance = math::length(orbit.camera().eye);
+
+    // a quarter of the canvas is a quarter turn
+    orbit.rotate(-100.0, 0.0, 400.0);
+    orbit.update();
+    let first = orbit.camera().eye;
+    settle(&mut orbit);
+    let last = orbit.camera().eye;
+
+    let turned = |eye: [f32; 3]| eye[0].atan2(eye[2]) - 75.0_f32.atan2(0.0);
+    assert!((turned(first) - PI / 8.0).abs() < 1e-3, "{}", turned(first));
+    assert!((turned(last) - PI / 2.0).abs() < 1e-3, "{}", turned(last));
+    assert!((math::length(last) - distance).abs() < 1e-3);
+}
+
+#[test]
+fn the_camera_never_goes_under_the_ground() {
+    let mut orbit = lights_camera();
+    orbit.pointer_down(OrbitAction::Rotate, 0.0, 0.0);
+    orbit.pointer_move(0.0, -2000.0, 400.0);
+    orbit.pointer_up();
+    settle(&mut orbit);
+
+    assert!(orbit.camera().eye[1] > -1e-4, "{:?}", orbit.camera().eye);
+    assert!(!orbit.dragging());
+}
+
+#[test]
+fn zooming_moves_in_or_shrinks_the_view() {
+    let mut orbit = lights_camera();
+    let distance = math::length(orbit.camera().eye);
+    orbit.zoom(-100.0);
+    orbit.update();
+    assert!((math::length(orbit.camera().eye) - distance * 0.95).abs() < 1e-3);
+
+    let mut orbit = OrbitControls::new(Camera::orthographic(10.0, 0.1, 100.0).looking_at([0.0, 0.0, 10.0], [0.0; 3]));
+    orbit.zoom(100.0);
+    orbit.update();
+    match orbit.camera().projection {
+        Projection::Orthographic { height, .. } => assert!((height - 10.0 / 0.95).abs() < 1e-4),
+        projection => panic!("{:?}", projection),
+    }
+}
+
+#[test]
+fn panning_slides_along_the_ground() {
+    let mut orbit = lights_camera();
+    orbit.pointer_down(OrbitAction::Pan, 0.0, 0.0);
+    orbit.pointer_move(50.0, 50.0, 400.0);
+    settle(&mut orbit);
+
+    let camera = orbit.camera();
+    assert!(camera.target[1].abs() < 1e-4, "{:?}", camera.target);
+    assert!(camera.target[0] < 0.0 && camera.target[2] > 0.0, "{:?}", camera.target);
+    assert!(close(math::sub(camera.eye, camera.target), [75.0, 30.0, 0.0]));
+}
+
+#[test]
+fn disabled_controls_ignore_the_mouse() {
+    let mut orbit = lights_camera();
+    orbit.enabled = false;
+    orbit.pointer_down(OrbitAction::Rotate, 0.0, 0.0);
+    orbit.pointer_move(100.0, 100.0, 400.0);
+    orbit.zoom(-1.0);
+    settle(&mut orbit);
+    assert!(close(orbit.camera().eye, [75.0, 30.0, 0.0]));
+}
+
+#[test]
+fn rays_pass_through_what_the_camera_shows_there() {
+    let point = [4.0, -2.0, 3.0];
+    for camera in [
+        Camera::perspective(1.0, 0.1, 100.0).looking_at([3.0, 4.0, 25.0], [1.0, 0.0, -1.0]),
+        Camera::orthographic(10.0, 1.0, 50.0).looking_at([0.0, 5.0, 20.0], [0.0; 3]),
+    ] {
+        let clip = math::transform_point(&camera.view_projection(1.5), point);
+        let (origin, direction) = camera.ray([clip[0], clip[1]], 1.5);
+
+        // the point is on the ray, in front of the camera
+        let along = math::dot(math::sub(point, origin), direction);
+        assert!(along > 0.0);
+        assert!(close(math::add(origin, math::scale(direction, along)), point), "{:?}", camera.projection);
+    }
+}
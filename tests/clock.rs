@@ -0,0 +1,71 @@
+use webassembly_webgl_viewer::clock::{self, Clock, FixedStep, MAX_FRAME_TIME, TICK};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn frames_take_as_long_as_the_timestamps_between_them() {
+    let mut clock = Clock::new();
+
+    // nothing to measure the first frame against
+    assert_eq!(clock.tick_at(1000.0), 0.0);
+    assert!(close(clock.tick_at(1016.0), 0.016));
+    assert!(close(clock.delta_ticks(), 0.016 / TICK));
+    assert!(close(clock.tick_at(1024.0), 0.008));
+    assert!(close(clock.elapsed(), 0.024));
+
+    // a tab left in the background for a minute
+    assert!(close(clock.tick_at(61024.0), MAX_FRAME_TIME));
+}
+
+#[test]
+fn pausing_and_scaling_apply_to_every_clock() {
+    let (mut a, mut b) = (Clock::new(), Clock::new());
+    a.tick_at(0.0);
+    b.tick_at(0.0);
+
+    clock::pause();
+    assert!(clock::is_paused());
+    assert_eq!(a.tick_at(100.0), 0.0);
+    assert!(close(a.real_delta(), 0.1));
+
+    // the pause itself isn't caught up on afterwards
+    clock::resume();
+    assert!(close(a.tick_at(110.0), 0.01));
+
+    clock::set_time_scale(2.0);
+    assert!(close(b.tick_at(120.0), 0.24));
+    clock::set_time_scale(f64::NAN);
+    clock::set_time_scale(-1.0);
+    assert_eq!(clock::time_scale(), 0.0);
+}
+
+#[test]
+fn fixed_steps_carry_the_remainder_over() {
+    let mut stepper = FixedStep::new(0.01);
+    assert_eq!(stepper.advance(0.025), 2);
+    assert_eq!(stepper.advance(0.005), 1);
+    assert_eq!(stepper.advance(0.0), 0);
+    assert_eq!(stepper.advance(f64::NAN), 0);
+
+    // scaled time is stepped through in full
+    assert_eq!(stepper.advance(2.5), 250);
+}
+
+#[test]
+fn long_frames_are_cut_short_before_scaling() {
+    let mut clock = Clock::new();
+    let mut stepper = FixedStep::new(0.01);
+    clock::set_time_scale(10.0);
+
+    // a tab left in the background still only catches up one long frame, sped up
+    clock.tick_at(0.0);
+    assert!(close(clock.tick_at(60000.0), MAX_FRAME_TIME * 10.0));
+    assert!(close(clock.real_delta(), MAX_FRAME_TIME));
+    assert_eq!(stepper.advance(clock.delta()), (MAX_FRAME_TIME * 10.0 / 0.01).round() as usize);
+
+    // and a 30 Hz frame runs ten times the steps it would unscaled
+    assert!(close(clock.tick_at(60000.0 + 1000.0 / 30.0), 10.0 / 30.0));
+    assert_eq!(stepper.advance(clock.delta()), 33);
+}
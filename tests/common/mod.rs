@@ -0,0 +1,26 @@
+// helpers shared by the tests that draw the drag demo, not every test file uses all of them
+#![allow(dead_code)]
+
+use webassembly_webgl_viewer::drag;
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+
+pub const SIZE: usize = 128;
+
+// the pixel under a point in clip space
+pub fn pixel_at(renderer: &CpuRenderer, x: f32, y: f32) -> [u8; 4] {
+    let px = ((x + 1.0) * 0.5 * renderer.width() as f32) as usize;
+    let py = ((1.0 - y) * 0.5 * renderer.height() as f32) as usize;
+    renderer.pixel(px, py)
+}
+
+pub fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+}
+
+// draw the drag demo with the cursor parked at `cursor`, a corner that keeps it out of the way
+pub fn render(cursor: [f32; 2]) -> CpuRenderer {
+    drag::drag_mouse_move(cursor[0], cursor[1], false);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    drag::render(&mut renderer).unwrap();
+    renderer
+}
@@ -0,0 +1,109 @@
+use webassembly_webgl_viewer::error::ViewerError;
+use webassembly_webgl_viewer::renderer::{CpuRenderer, Primitive, Renderer};
+use webassembly_webgl_viewer::{gradient, special, square, triangle};
+
+// a quad covering the whole canvas, ordered for a strip
+const QUAD_STRIP: [f32; 20] = [
+    -1.0, -1.0, 1.0, 0.0, 0.0,
+    1.0, -1.0, 1.0, 0.0, 0.0,
+    -1.0, 1.0, 1.0, 0.0, 0.0,
+    1.0, 1.0, 1.0, 0.0, 0.0,
+];
+
+// the same quad ordered for a fan
+const QUAD_FAN: [f32; 20] = [
+    -1.0, -1.0, 1.0, 0.0, 0.0,
+    1.0, -1.0, 1.0, 0.0, 0.0,
+    1.0, 1.0, 1.0, 0.0, 0.0,
+    -1.0, 1.0, 1.0, 0.0, 0.0,
+];
+
+#[test]
+fn clear_fills_every_pixel() {
+    let mut renderer = CpuRenderer::new(4, 3);
+    renderer.clear([0.0, 0.0, 1.0, 1.0]);
+    assert!(renderer.pixels().chunks(4).all(|p| p == [0, 0, 255, 255]));
+}
+
+#[test]
+fn full_screen_quad_covers_every_pixel() {
+    let mut renderer = CpuRenderer::new(16, 16);
+    renderer.draw_vertices(Primitive::TriangleStrip, &QUAD_STRIP).unwrap();
+    assert!(renderer.pixels().chunks(4).all(|p| p == [255, 0, 0, 255]));
+}
+
+#[test]
+fn fan_and_strip_draw_the_same_quad() {
+    let mut strip = CpuRenderer::new(32, 32);
+    strip.draw_vertices(Primitive::TriangleStrip, &QUAD_STRIP).unwrap();
+
+    let mut fan = CpuRenderer::new(32, 32);
+    fan.draw_vertices(Primitive::TriangleFan, &QUAD_FAN).unwrap();
+
+    assert_eq!(strip.pixels(), fan.pixels());
+}
+
+#[test]
+fn colours_are_interpolated_across_a_triangle() {
+    let mut renderer = CpuRenderer::new(90, 90);
+    renderer.draw_vertices(Primitive::Triangles, &[
+        -1.0, -1.0, 1.0, 0.0, 0.0,
+        1.0, -1.0, 0.0, 1.0, 0.0,
+        -1.0, 1.0, 0.0, 0.0, 1.0,
+    ]).unwrap();
+
+    // near each corner the corner's colour dominates
+    assert!(renderer.pixel(0, 89)[0] > 240);
+    assert!(renderer.pixel(88, 89)[1] > 240);
+    assert!(renderer.pixel(0, 1)[2] > 240);
+
+    // the centroid is an even mix
+    let [r, g, b, a] = renderer.pixel(30, 59);
+    assert!([r, g, b].iter().all(|c| (80..=95).contains(c)), "{:?}", [r, g, b]);
+    assert_eq!(a, 255);
+
+    // outside the triangle is untouched
+    assert_eq!(renderer.pixel(89, 0), [0, 0, 0, 0]);
+}
+
+#[test]
+fn draw_respects_first_and_count() {
+    let mut renderer = CpuRenderer::new(8, 8);
+    renderer.upload(&QUAD_STRIP).unwrap();
+    renderer.draw(Primitive::Triangles, 1, 3);
+
+    // one triangle only covers part of the canvas
+    let covered = renderer.pixels().chunks(4).filter(|p| p[3] == 255).count();
+    assert!(covered > 0 && covered < 64);
+}
+
+#[test]
+fn demos_render_without_a_browser() {
+    let mut renderer = CpuRenderer::new(64, 64);
+
+    triangle::render(&mut renderer, &[0.0, 1.0, 0.0, 1.0]).unwrap();
+    assert_eq!(renderer.pixel(32, 40), [0, 255, 0, 255]);
+
+    square::render(&mut renderer, &[0.0, 0.0, 1.0, 1.0]).unwrap();
+    assert_eq!(renderer.pixel(32, 32), [0, 0, 255, 255]);
+    assert_eq!(renderer.pixel(2, 2), [0, 0, 0, 0]);
+
+    special::render(&mut renderer).unwrap();
+    assert_eq!(renderer.pixel(32, 32)[3], 255);
+
+    gradient::render(&mut renderer).unwrap();
+    assert_eq!(renderer.pixel(32, 32)[3], 255);
+}
+
+#[test]
+fn short_or_translucent_colours_are_errors() {
+    let mut renderer = CpuRenderer::new(8, 8);
+
+    assert!(matches!(triangle::render(&mut renderer, &[1.0, 0.0]), Err(ViewerError::InvalidColor(_))));
+    assert!(matches!(square::render(&mut renderer, &[]), Err(ViewerError::InvalidColor(_))));
+    assert!(matches!(square::render(&mut renderer, &[1.0, 0.0, 0.0, 0.5]), Err(ViewerError::InvalidColor(_))));
+
+    // rgb on its own is fine
+    square::render(&mut renderer, &[1.0, 0.0, 0.0]).unwrap();
+    assert_eq!(renderer.pixel(4, 4), [255, 0, 0, 255]);
+}
@@ -0,0 +1,101 @@
+use webassembly_webgl_viewer::scene::Scene;
+use webassembly_webgl_viewer::{drag, history};
+
+mod common;
+
+use common::{close, pixel_at, render};
+
+// where the handle on a vertex starts, from its bottom left corner
+fn origin(space: usize, vertex: usize) -> [f32; 2] {
+    Scene::current().drag.spaces[space].vertices[vertex].origin
+}
+
+#[test]
+fn tab_walks_every_vertex_then_lets_go() {
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    assert_eq!(drag::drag_focus(), None);
+
+    let mut seen = Vec::new();
+    while drag::drag_key("Tab", false) {
+        seen.push(drag::drag_focus().unwrap());
+    }
+    assert_eq!(seen, vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 0), (1, 1), (1, 2), (1, 3)]);
+    assert_eq!(drag::drag_focus(), None);
+
+    // shift+tab goes back from the end
+    assert!(drag::drag_key("Tab", true));
+    assert_eq!(drag::drag_focus(), Some((1, 3)));
+    assert!(drag::drag_key("Escape", false));
+    assert_eq!(drag::drag_focus(), None);
+}
+
+#[test]
+fn arrows_nudge_the_focused_vertex_and_can_be_undone() {
+    // nothing focused, nothing to nudge
+    assert!(!drag::drag_key("ArrowRight", false));
+
+    drag::drag_focus_next(false);
+    let start = origin(0, 0);
+
+    assert!(drag::drag_key("ArrowRight", false));
+    assert!(drag::drag_key("ArrowUp", true));
+    assert!(close(origin(0, 0), [start[0] + 0.01, start[1] + 0.1]));
+
+    assert!(history::undo());
+    assert!(close(origin(0, 0), [start[0] + 0.01, start[1]]));
+    assert!(history::undo());
+    assert!(close(origin(0, 0), start));
+}
+
+#[test]
+fn nudging_a_box_corner_keeps_it_a_box() {
+    drag::drag_focus_next(false);
+    let before: Vec<[f32; 2]> = (0..4).map(|v| origin(0, v)).collect();
+    drag::drag_key("ArrowLeft", true);
+    let after: Vec<[f32; 2]> = (0..4).map(|v| origin(0, v)).collect();
+
+    // the box still has two distinct xs and two distinct ys
+    let mut xs: Vec<f32> = after.iter().map(|p| p[0]).collect();
+    let mut ys: Vec<f32> = after.iter().map(|p| p[1]).collect();
+    xs.sort_by(f32::total_cmp);
+    xs.dedup_by(|a, b| (*a - *b).abs() < 1e-5);
+    ys.sort_by(f32::total_cmp);
+    ys.dedup_by(|a, b| (*a - *b).abs() < 1e-5);
+    assert_eq!((xs.len(), ys.len()), (2, 2));
+    assert_ne!(before, after);
+}
+
+#[test]
+fn delete_removes_a_polygon_vertex_or_a_whole_box() {
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    drag::drag_focus_next(false);
+    assert!(drag::drag_key("Delete", false));
+    assert_eq!(drag::drag_space_count(), 1);
+
+    drag::drag_set_polygon(true);
+    drag::drag_focus_next(false);
+    let count = Scene::current().drag.spaces[0].vertices.len();
+    assert!(drag::drag_key("Backspace", false));
+    assert_eq!(Scene::current().drag.spaces[0].vertices.len(), count - 1);
+    assert!(drag::drag_focus().is_some());
+
+    // a triangle is as small as a polygon gets
+    while Scene::current().drag.spaces[0].vertices.len() > 3 {
+        drag::drag_delete_focused();
+    }
+    drag::drag_delete_focused();
+    assert_eq!(Scene::current().drag.spaces[0].vertices.len(), 3);
+}
+
+#[test]
+fn the_focused_vertex_gets_a_ring() {
+    drag::drag_key("Tab", false);
+    let [x, y] = origin(0, 0);
+
+    // park the cursor in a corner so it stays out of the way
+    let renderer = render([-0.95, 0.95]);
+
+    // just outside the handle is the ring, not the handle's own colour or white
+    let ring = pixel_at(&renderer, x - 0.01, y + 0.05);
+    assert_eq!(&ring[..3], &[255, 140, 0]);
+}
@@ -0,0 +1,90 @@
+use webassembly_webgl_viewer::drag;
+
+mod common;
+
+use common::{pixel_at, render};
+
+// twice the area covered by a set of triangles
+fn area(points: &[[f32; 2]], triangles: &[[usize; 3]]) -> f32 {
+    triangles.iter().map(|[a, b, c]| {
+        let (a, b, c) = (points[*a], points[*b], points[*c]);
+        ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])).abs()
+    }).sum()
+}
+
+#[test]
+fn convex_polygons_become_a_fan_of_triangles() {
+    let square = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    let triangles = drag::triangulate(&square);
+    assert_eq!(triangles.len(), 2);
+    assert!((area(&square, &triangles) - 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn concave_polygons_are_clipped_inside_the_outline() {
+    // an L shape, walked clockwise
+    let l = [[0.0, 0.0], [0.0, 2.0], [1.0, 2.0], [1.0, 1.0], [2.0, 1.0], [2.0, 0.0]];
+    let triangles = drag::triangulate(&l);
+    assert_eq!(triangles.len(), l.len() - 2);
+    assert!((area(&l, &triangles) - 6.0).abs() < 1e-6);
+
+    // nothing covers the missing corner
+    for [a, b, c] in triangles {
+        let centroid = [
+            (l[a][0] + l[b][0] + l[c][0]) / 3.0,
+            (l[a][1] + l[b][1] + l[c][1]) / 3.0,
+        ];
+        assert!(!(centroid[0] > 1.0 && centroid[1] > 1.0));
+    }
+}
+
+#[test]
+fn too_few_points_make_no_triangles() {
+    assert!(drag::triangulate(&[[0.0, 0.0], [1.0, 0.0]]).is_empty());
+}
+
+#[test]
+fn clicking_an_edge_inserts_a_vertex_that_can_be_dragged() {
+    drag::drag_set_polygon(true);
+
+    // the left edge of the starting square runs along x = -0.45
+    drag::drag_mouse_down(-0.45, 0.05, false);
+    drag::drag_mouse_move(-0.45, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, false);
+
+    // the filled outline now reaches out past the old edge
+    let renderer = render([0.95, -0.95]);
+    assert_ne!(pixel_at(&renderer, -0.7, 0.05), [0, 0, 0, 0]);
+    assert_eq!(pixel_at(&renderer, -0.7, 0.45), [0, 0, 0, 0]);
+
+    // and removing the vertex pulls it back in
+    drag::drag_mouse_down(-0.9, 0.05, true);
+    let renderer = render([0.95, -0.95]);
+    assert_eq!(pixel_at(&renderer, -0.7, 0.05), [0, 0, 0, 0]);
+}
+
+#[test]
+fn polygons_keep_at_least_three_verticies() {
+    drag::drag_set_polygon(true);
+
+    drag::drag_mouse_down(-0.45, -0.45, true);
+    drag::drag_mouse_down(0.55, 0.55, true);
+
+    // the second removal is refused, so the triangle left is still drawn
+    let renderer = render([0.95, -0.95]);
+    assert_ne!(pixel_at(&renderer, 0.3, 0.3), [0, 0, 0, 0]);
+}
+
+#[test]
+fn edge_clicks_do_nothing_outside_polygon_mode() {
+    drag::drag_mouse_down(-0.45, 0.05, false);
+    drag::drag_mouse_move(-0.45, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, false);
+
+    // nothing was picked up, the square is untouched
+    let renderer = render([0.95, -0.95]);
+    assert_eq!(pixel_at(&renderer, -0.7, 0.05), [0, 0, 0, 0]);
+    assert_ne!(pixel_at(&renderer, -0.3, 0.05), [0, 0, 0, 0]);
+}
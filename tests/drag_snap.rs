@@ -0,0 +1,84 @@
+use webassembly_webgl_viewer::drag;
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+use webassembly_webgl_viewer::scene::Scene;
+use webassembly_webgl_viewer::share::{self, Demo};
+
+mod common;
+
+use common::{close, pixel_at, SIZE};
+
+// the center of a vertex's handle
+fn center(space: usize, vertex: usize) -> [f32; 2] {
+    let v = &Scene::current().drag.spaces[space].vertices[vertex];
+    [v.origin[0] + v.size[0] / 2.0, v.origin[1] + v.size[1] / 2.0]
+}
+
+#[test]
+fn an_axis_lines_up_before_it_rounds_to_the_grid() {
+    let (value, guide) = drag::snap_axis(0.33, 0.1, &[], 0.03);
+    assert!((value - 0.3).abs() < 1e-6);
+    assert_eq!(guide, None);
+
+    // the closest target in reach wins over the grid
+    assert_eq!(drag::snap_axis(0.33, 0.1, &[0.36, 0.31, 0.9], 0.03), (0.31, Some(0.31)));
+
+    // out of reach, and with no grid, it stays where it is
+    assert_eq!(drag::snap_axis(0.33, 0.0, &[0.9], 0.03), (0.33, None));
+}
+
+#[test]
+fn dragging_lands_on_the_grid_only_while_snapping() {
+    // the first box's bottom left corner is at -0.45, -0.45
+    drag::drag_mouse_move(-0.45, -0.45, true);
+    drag::drag_mouse_move(-0.23, -0.58, true);
+    assert!(close(center(0, 0), [-0.23, -0.58]));
+
+    drag::drag_set_snap(0.25, true);
+    drag::drag_mouse_move(-0.23, -0.58, true);
+    assert!(close(center(0, 0), [-0.25, -0.5]));
+
+    // the rest of the box keeps up with the corner
+    assert!(close([center(0, 1)[0], center(0, 3)[1]], [-0.25, -0.5]));
+    drag::drag_mouse_move(-0.23, -0.58, false);
+}
+
+#[test]
+fn corners_line_up_with_other_spaces_and_show_a_guide() {
+    drag::drag_set_snap(0.5, true);
+
+    // a box off to the right, from -0.9 up to -0.5
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.4);
+
+    // bring the first box's top right corner near its centre line, -0.7
+    drag::drag_mouse_move(0.55, 0.55, true);
+    drag::drag_mouse_move(0.3, -0.72, true);
+    assert!(close(center(0, 2), [0.5, -0.7]));
+
+    // the guide runs across the whole canvas along the line it matched
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    drag::render(&mut renderer).unwrap();
+    assert_eq!(&pixel_at(&renderer, -0.95, -0.7)[..3], &[0, 153, 255]);
+
+    // and goes away once the corner is let go
+    drag::drag_mouse_move(0.3, -0.72, false);
+    drag::render(&mut renderer).unwrap();
+    assert_ne!(&pixel_at(&renderer, -0.95, -0.7)[..3], &[0, 153, 255]);
+}
+
+#[test]
+fn snap_settings_are_shared_and_bad_spacings_ignored() {
+    drag::drag_set_snap(0.2, true);
+    drag::drag_set_snap(f32::NAN, true);
+    drag::drag_set_snap(-1.0, true);
+    assert_eq!(drag::drag_snap_spacing(), 0.2);
+    drag::drag_set_snap(100.0, true);
+    assert_eq!(drag::drag_snap_spacing(), drag::MAX_SNAP_SPACING);
+
+    drag::drag_set_snap(0.2, true);
+    let hash = share::encode(Demo::Drag);
+    drag::drag_set_snap(drag::DEFAULT_SNAP_SPACING, false);
+
+    share::decode(&hash);
+    assert!(drag::drag_snap_enabled());
+    assert_eq!(drag::drag_snap_spacing(), 0.2);
+}
@@ -0,0 +1,61 @@
+use webassembly_webgl_viewer::drag;
+
+mod common;
+
+use common::{pixel_at, render};
+
+const BLANK: [u8; 4] = [0, 0, 0, 0];
+
+fn drag_from_to(from: (f32, f32), to: (f32, f32)) {
+    drag::drag_mouse_move(from.0, from.1, true);
+    drag::drag_mouse_move(to.0, to.1, true);
+    drag::drag_mouse_move(to.0, to.1, false);
+}
+
+#[test]
+fn dragging_a_corner_only_moves_its_own_space() {
+    // a second box away from the first, which spans -0.45 to 0.55
+    assert_eq!(drag::drag_add_space(0.6, -0.9, 0.3, 0.3), 1);
+
+    // pull its top right corner up
+    drag_from_to((0.9, -0.6), (0.9, -0.3));
+
+    let renderer = render([-0.95, -0.95]);
+    assert_ne!(pixel_at(&renderer, 0.75, -0.45), BLANK);
+
+    // the matching corner of the first box stayed put
+    assert_ne!(pixel_at(&renderer, 0.0, 0.3), BLANK);
+    assert_eq!(pixel_at(&renderer, 0.62, 0.0), BLANK);
+}
+
+#[test]
+fn the_topmost_space_wins_a_shared_corner() {
+    // the new box's bottom left sits on the first box's top right
+    drag::drag_add_space(0.55, 0.55, 0.3, 0.3);
+    drag_from_to((0.55, 0.55), (0.7, 0.7));
+
+    let renderer = render([-0.95, -0.95]);
+
+    // the new box shrank away from the shared corner
+    assert_eq!(pixel_at(&renderer, 0.63, 0.63), BLANK);
+    assert_ne!(pixel_at(&renderer, 0.77, 0.77), BLANK);
+
+    // and the first box was left alone
+    assert_ne!(pixel_at(&renderer, 0.45, 0.45), BLANK);
+    assert_eq!(pixel_at(&renderer, 0.62, 0.3), BLANK);
+}
+
+#[test]
+fn spaces_can_be_removed() {
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    assert_eq!(drag::drag_space_count(), 2);
+
+    assert!(drag::drag_remove_space(0));
+    assert!(!drag::drag_remove_space(5));
+    assert_eq!(drag::drag_space_count(), 1);
+
+    // the first box is gone and the second is still drawn
+    let renderer = render([-0.95, -0.95]);
+    assert_eq!(pixel_at(&renderer, 0.0, 0.0), BLANK);
+    assert_ne!(pixel_at(&renderer, 0.75, -0.75), BLANK);
+}
@@ -0,0 +1,190 @@
+// Renders every demo with the CPU backend and compares it against the
+// reference images in tests/golden. Run with UPDATE_GOLDENS=1 to rewrite them.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use webassembly_webgl_viewer::clock::TICK;
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+use webassembly_webgl_viewer::seed::{current_seed, set_seed};
+use webassembly_webgl_viewer::{drag, gradient, point, shakes, sin_wave, special, square, triangle};
+
+const SIZE: usize = 128;
+
+// largest difference allowed in any channel of a pixel
+const TOLERANCE: u8 = 4;
+
+// pixels on triangle edges may flip between platforms
+const MAX_MISMATCHED_PIXELS: usize = SIZE * SIZE / 500;
+
+const SEED: u64 = 1234;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", name))
+}
+
+fn write_png(path: &PathBuf, renderer: &CpuRenderer) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let file = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, renderer.width() as u32, renderer.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(renderer.pixels()).unwrap();
+}
+
+fn read_png(path: &PathBuf) -> (usize, usize, Vec<u8>) {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels.truncate(info.buffer_size());
+    (info.width as usize, info.height as usize, pixels)
+}
+
+fn assert_matches_golden(name: &str, renderer: &CpuRenderer) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        write_png(&path, renderer);
+        return;
+    }
+    assert!(
+        path.exists(),
+        "no golden image for {} at {}, run with UPDATE_GOLDENS=1 to create it",
+        name,
+        path.display()
+    );
+
+    let (width, height, expected) = read_png(&path);
+    assert_eq!((width, height), (renderer.width(), renderer.height()), "{} changed size", name);
+
+    let mismatched = renderer.pixels()
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .filter(|(actual, expected)| {
+            actual.iter().zip(expected.iter()).any(|(a, e)| a.abs_diff(*e) > TOLERANCE)
+        })
+        .count();
+
+    if mismatched > MAX_MISMATCHED_PIXELS {
+        let actual_path = path.with_extension("actual.png");
+        write_png(&actual_path, renderer);
+        panic!(
+            "{} differs from its golden image in {} pixels, see {}",
+            name, mismatched, actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn triangle_matches_golden() {
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    triangle::render(&mut renderer, &[1.0, 0.5, 0.9, 1.0]).unwrap();
+    assert_matches_golden("triangle", &renderer);
+}
+
+#[test]
+fn square_matches_golden() {
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    square::render(&mut renderer, &[0.0, 0.5, 0.9, 1.0]).unwrap();
+    assert_matches_golden("square", &renderer);
+}
+
+#[test]
+fn special_matches_golden() {
+    special::update_sides(6);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    special::render(&mut renderer).unwrap();
+    assert_matches_golden("special", &renderer);
+}
+
+#[test]
+fn gradient_matches_golden() {
+    gradient::g_update_sides(10);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    gradient::render(&mut renderer).unwrap();
+    assert_matches_golden("gradient", &renderer);
+}
+
+#[test]
+fn point_matches_golden() {
+    point::p_update_resolution(4);
+    point::reseed(SEED);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    point::render(&mut renderer).unwrap();
+    assert_matches_golden("point", &renderer);
+}
+
+#[test]
+fn point_pointwise_matches_golden() {
+    point::p_update_resolution(4);
+    point::p_update_box(true);
+    point::reseed(SEED);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    point::render(&mut renderer).unwrap();
+    assert_matches_golden("point_pointwise", &renderer);
+}
+
+#[test]
+fn sin_wave_matches_golden() {
+    sin_wave::s_update_resolution(16);
+    sin_wave::s_update_wavelength(10.0);
+    sin_wave::reseed(SEED);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    sin_wave::render(&mut renderer).unwrap();
+    assert_matches_golden("sin_wave", &renderer);
+}
+
+#[test]
+fn drag_matches_golden() {
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    drag::render(&mut renderer).unwrap();
+    assert_matches_golden("drag", &renderer);
+}
+
+#[test]
+fn shakes_matches_golden() {
+    shakes::reseed(SEED);
+    shakes::shakes_step(TICK);
+    let mut renderer = CpuRenderer::new(SIZE, SIZE);
+    shakes::render(&mut renderer).unwrap();
+    assert_matches_golden("shakes", &renderer);
+}
+
+#[test]
+fn seeded_demos_render_the_same_twice() {
+    let mut first = CpuRenderer::new(SIZE, SIZE);
+    point::p_update_resolution(3);
+    point::reseed(SEED);
+    point::render(&mut first).unwrap();
+
+    let mut second = CpuRenderer::new(SIZE, SIZE);
+    point::reseed(SEED);
+    point::render(&mut second).unwrap();
+
+    assert_eq!(first.pixels(), second.pixels());
+}
+
+#[test]
+fn set_seed_replays_the_random_demos() {
+    sin_wave::s_update_resolution(6);
+    point::p_update_resolution(3);
+    set_seed(42);
+    assert_eq!(current_seed(), 42);
+
+    let mut first = CpuRenderer::new(SIZE, SIZE);
+    point::render(&mut first).unwrap();
+    sin_wave::render(&mut first).unwrap();
+
+    point::p_update_box(true);
+    point::p_update_box(false);
+    set_seed(42);
+    let mut second = CpuRenderer::new(SIZE, SIZE);
+    point::render(&mut second).unwrap();
+    sin_wave::render(&mut second).unwrap();
+
+    assert_eq!(first.pixels(), second.pixels());
+}
@@ -0,0 +1,113 @@
+use webassembly_webgl_viewer::grid::GridMesh;
+use webassembly_webgl_viewer::renderer::VERTEX_STRIDE;
+use palette::Srgb;
+
+fn grey(value: f32) -> Srgb {
+    Srgb::new(value, value, value)
+}
+
+#[test]
+fn vertex_counts_follow_the_resolution() {
+    for resolution in 1..6 {
+        let grid = GridMesh::new(resolution);
+        let side = resolution as usize;
+
+        assert_eq!(grid.cells().count(), side * side);
+        assert_eq!(grid.cell_count(), side * side);
+        assert_eq!(grid.corner_count(), (side + 1) * (side + 1));
+        assert_eq!(grid.indices().len(), side * side * 6);
+
+        let shared = grid.shared_vertices(|_, _| grey(0.5));
+        assert_eq!(shared.len(), grid.corner_count() * VERTEX_STRIDE);
+        let separate = grid.cell_vertices(|_, _, _| grey(0.5));
+        assert_eq!(separate.len(), grid.indices().len() * VERTEX_STRIDE);
+    }
+}
+
+#[test]
+fn corners_cover_clip_space() {
+    let grid = GridMesh::new(3);
+    let corners = grid.corners();
+
+    assert!(corners.iter().all(|v| (-1.0..=1.0).contains(v)));
+    assert_eq!(corners.iter().cloned().fold(f32::MAX, f32::min), -1.0);
+    assert_eq!(corners.iter().cloned().fold(f32::MIN, f32::max), 1.0);
+
+    // neighbouring corners are one cell apart
+    let [x0, y0] = grid.corner(0);
+    let [x1, y1] = grid.corner(1);
+    assert_eq!(x0, x1);
+    assert!((y1 - y0 - grid.cell_size()).abs() < 1e-6);
+}
+
+#[test]
+fn neighbouring_cells_share_corners() {
+    let grid = GridMesh::new(4);
+    let indices = grid.indices();
+
+    // every corner is used, and interior corners by all four cells around them
+    let mut uses = vec![0; grid.corner_count()];
+    for cell in indices.chunks_exact(6) {
+        let mut corners = cell.to_vec();
+        corners.sort();
+        corners.dedup();
+        assert_eq!(corners.len(), 4);
+        for corner in corners {
+            uses[corner as usize] += 1;
+        }
+    }
+    assert!(uses.iter().all(|n| *n >= 1));
+    assert_eq!(uses.iter().filter(|n| **n == 4).count(), 3 * 3);
+
+    // a shared corner gets one colour, whichever cell it is drawn for
+    let shared = grid.shared_vertices(|i, _| grey(i as f32));
+    for corner in indices {
+        let v = &shared[*corner as usize * VERTEX_STRIDE..];
+        assert_eq!(v[2], *corner as f32);
+    }
+}
+
+#[test]
+fn cell_vertices_can_be_coloured_per_cell() {
+    let grid = GridMesh::new(2);
+    let data = grid.cell_vertices(|cell, _, _| grey(cell as f32));
+
+    for (i, vertex) in data.chunks_exact(VERTEX_STRIDE).enumerate() {
+        assert_eq!(vertex[2], (i / 6) as f32);
+    }
+
+    // neighbouring cells draw the corner they share in their own colours, which
+    // shared vertices can't do
+    let stride = 6 * VERTEX_STRIDE;
+    let corner_in = |cell: usize, corner: [f32; 2]| {
+        data[cell * stride..(cell + 1) * stride]
+            .chunks_exact(VERTEX_STRIDE)
+            .find(|vertex| [vertex[0], vertex[1]] == corner)
+            .map(|vertex| vertex[2])
+    };
+    let middle = [0.0, 0.0];
+    let colours: Vec<_> = (0..grid.cell_count()).map(|cell| corner_in(cell, middle).unwrap()).collect();
+    assert_eq!(colours, vec![0.0, 1.0, 2.0, 3.0]);
+
+    // positions match the indexed corners
+    for (vertex, corner) in data.chunks_exact(VERTEX_STRIDE).zip(grid.indices()) {
+        assert_eq!([vertex[0], vertex[1]], grid.corner(*corner as usize));
+    }
+}
+
+#[test]
+fn corners_near_each_other_keep_their_own_colours_at_high_resolutions() {
+    // corners either side of the middle are under a thousandth from it, which
+    // colours keyed by rounded positions used to merge into one
+    let grid = GridMesh::new(1001);
+    let side = grid.resolution() as usize + 1;
+    let (left, right) = (500 * side + 500, 501 * side + 500);
+    let ([x0, y0], [x1, y1]) = (grid.corner(left), grid.corner(right));
+    assert!(x0.abs() < 0.001 && x1.abs() < 0.001);
+    assert_ne!(x0, x1);
+    assert_eq!(y0, y1);
+
+    let shared = grid.shared_vertices(|i, _| grey(i as f32));
+    assert_eq!(shared[left * VERTEX_STRIDE + 2], left as f32);
+    assert_eq!(shared[right * VERTEX_STRIDE + 2], right as f32);
+}
@@ -0,0 +1,134 @@
+use webassembly_webgl_viewer::history::{self, HISTORY_LIMIT};
+use webassembly_webgl_viewer::{drag, shakes};
+
+mod common;
+
+const BLANK: [u8; 4] = [0, 0, 0, 0];
+
+// the pixel under a point in clip space, with the drag cursor out of the way
+fn drag_pixel_at(x: f32, y: f32) -> [u8; 4] {
+    common::pixel_at(&common::render([-0.95, -0.95]), x, y)
+}
+
+#[test]
+fn nothing_to_undo_at_the_start() {
+    assert!(!history::can_undo());
+    assert!(!history::can_redo());
+    assert!(!history::undo());
+    assert!(!history::redo());
+}
+
+#[test]
+fn added_rects_can_be_undone_and_redone() {
+    let start = shakes::shakes_rect_count();
+
+    shakes::shakes_mouse_down(0.2, 0.2, false);
+    assert_eq!(shakes::shakes_rect_count(), start + 1);
+    assert!(history::can_undo());
+
+    assert!(history::undo());
+    assert_eq!(shakes::shakes_rect_count(), start);
+    assert!(history::can_redo());
+
+    assert!(history::redo());
+    assert_eq!(shakes::shakes_rect_count(), start + 1);
+}
+
+#[test]
+fn removed_rects_come_back() {
+    let start = shakes::shakes_rect_count();
+
+    // inside the second starting rect
+    shakes::shakes_mouse_down(0.25, 0.25, true);
+    assert_eq!(shakes::shakes_rect_count(), start - 1);
+
+    history::undo();
+    assert_eq!(shakes::shakes_rect_count(), start);
+
+    // missing every rect removes nothing and records nothing
+    history::clear_history();
+    shakes::shakes_mouse_down(0.9, -0.9, true);
+    assert!(!history::can_undo());
+}
+
+#[test]
+fn dragging_a_corner_is_one_edit() {
+    // pull the top right corner of the starting box out
+    drag::drag_mouse_move(0.55, 0.55, true);
+    drag::drag_mouse_move(0.7, 0.7, true);
+    drag::drag_mouse_move(0.8, 0.8, true);
+    drag::drag_mouse_move(0.8, 0.8, false);
+    assert_ne!(drag_pixel_at(0.7, 0.0), BLANK);
+
+    assert!(history::undo());
+    assert!(!history::can_undo());
+    assert_eq!(drag_pixel_at(0.7, 0.0), BLANK);
+    assert_ne!(drag_pixel_at(0.0, 0.0), BLANK);
+
+    assert!(history::redo());
+    assert_ne!(drag_pixel_at(0.7, 0.0), BLANK);
+}
+
+#[test]
+fn spaces_added_and_removed_from_the_page_can_be_undone() {
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    drag::drag_remove_space(0);
+    assert_eq!(drag::drag_space_count(), 1);
+
+    // the first box comes back underneath, then the added one goes
+    history::undo();
+    assert_eq!(drag::drag_space_count(), 2);
+    assert_ne!(drag_pixel_at(0.0, 0.0), BLANK);
+
+    history::undo();
+    assert_eq!(drag::drag_space_count(), 1);
+    assert_eq!(drag_pixel_at(0.75, -0.75), BLANK);
+}
+
+#[test]
+fn polygon_edits_can_be_undone() {
+    drag::drag_set_polygon(true);
+
+    // add a vertex on the left edge and drag it out
+    drag::drag_mouse_down(-0.45, 0.05, false);
+    drag::drag_mouse_move(-0.45, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, true);
+    drag::drag_mouse_move(-0.9, 0.05, false);
+    assert_ne!(drag_pixel_at(-0.7, 0.05), BLANK);
+
+    // the drag and the insert are separate edits
+    history::undo();
+    assert_eq!(drag_pixel_at(-0.7, 0.05), BLANK);
+    assert!(history::can_undo());
+    history::undo();
+    assert!(!history::can_undo());
+
+    history::redo();
+    history::redo();
+    assert_ne!(drag_pixel_at(-0.7, 0.05), BLANK);
+}
+
+#[test]
+fn a_new_edit_forgets_what_was_undone() {
+    shakes::shakes_mouse_down(0.2, 0.2, false);
+    history::undo();
+    assert!(history::can_redo());
+
+    shakes::shakes_mouse_down(-0.2, -0.2, false);
+    assert!(!history::can_redo());
+}
+
+#[test]
+fn only_the_latest_edits_are_kept() {
+    let start = shakes::shakes_rect_count();
+    for i in 0..HISTORY_LIMIT + 20 {
+        shakes::shakes_mouse_down(i as f64 / 1000.0, 0.0, false);
+    }
+
+    let mut undone = 0;
+    while history::undo() {
+        undone += 1;
+    }
+    assert_eq!(undone, HISTORY_LIMIT);
+    assert_eq!(shakes::shakes_rect_count(), start + 20);
+}
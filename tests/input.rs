@@ -0,0 +1,141 @@
+use webassembly_webgl_viewer::camera::{self, Camera, OrbitControls};
+use webassembly_webgl_viewer::input::{Phase, Pointer, PointerKind, Pointers};
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+use webassembly_webgl_viewer::{drag, math, shakes};
+
+const SIZE: f32 = 200.0;
+
+// a pointer at a point in clip space on a square canvas
+fn pointer(id: i32, kind: PointerKind, x: f32, y: f32) -> Pointer {
+    Pointer::at(id, kind, [(x + 1.0) / 2.0 * SIZE, (1.0 - y) / 2.0 * SIZE], [SIZE, SIZE])
+}
+
+fn touch(id: i32, x: f32, y: f32) -> Pointer {
+    pointer(id, PointerKind::Touch, x, y)
+}
+
+#[test]
+fn pointers_are_mapped_onto_the_canvas() {
+    let p = Pointer::at(1, PointerKind::Pen, [50.0, 150.0], [200.0, 200.0]);
+    assert_eq!(p.position, [-0.5, -0.5]);
+    assert_eq!(PointerKind::from_name("touch"), PointerKind::Touch);
+    assert_eq!(PointerKind::from_name("pen"), PointerKind::Pen);
+    assert_eq!(PointerKind::from_name(""), PointerKind::Mouse);
+}
+
+#[test]
+fn presses_become_drags_once_they_move() {
+    let mut pointers = Pointers::new();
+
+    // a mouse hovering is the primary pointer, but not pressed
+    let hover = pointers.moved(pointer(1, PointerKind::Mouse, 0.0, 0.0));
+    assert!(hover.primary && !hover.pressed);
+
+    let down = pointers.down(touch(2, 0.0, 0.0));
+    assert_eq!((down.phase, down.pressed, down.dragging, down.active), (Phase::Down, true, false, 1));
+
+    // a wobble under the threshold is still a tap
+    let wobble = pointers.moved(Pointer::at(2, PointerKind::Touch, [101.0, 101.0], [SIZE, SIZE]));
+    assert!(wobble.pressed && !wobble.dragging);
+
+    let moved = pointers.moved(touch(2, 0.5, 0.5));
+    assert!(moved.dragging);
+
+    let up = pointers.up(touch(2, 0.5, 0.5)).unwrap();
+    assert_eq!((up.phase, up.dragging, up.active), (Phase::Up, true, 0));
+
+    // nothing to say about a pointer that never went down here
+    assert_eq!(pointers.up(touch(3, 0.0, 0.0)), None);
+}
+
+#[test]
+fn the_first_finger_down_is_primary() {
+    let mut pointers = Pointers::new();
+    assert!(pointers.down(touch(1, 0.0, 0.0)).primary);
+    let second = pointers.down(touch(2, 0.5, 0.0));
+    assert!(!second.primary);
+    assert_eq!(second.active, 2);
+
+    assert!(pointers.up(touch(1, 0.0, 0.0)).unwrap().primary);
+    assert!(pointers.moved(touch(2, 0.6, 0.0)).primary);
+    assert_eq!(pointers.active(), 1);
+}
+
+#[test]
+fn a_finger_drags_a_corner() {
+    let mut pointers = Pointers::new();
+
+    // the default box's top right corner, pulled out with a touch while a second finger rests
+    drag::drag_pointer(&pointers.down(touch(1, 0.55, 0.55)));
+    drag::drag_pointer(&pointers.down(touch(2, -0.8, -0.8)));
+    drag::drag_pointer(&pointers.moved(touch(2, -0.9, -0.9)));
+    drag::drag_pointer(&pointers.moved(touch(1, 0.85, 0.85)));
+    drag::drag_pointer(&pointers.up(touch(1, 0.85, 0.85)).unwrap());
+
+    drag::drag_mouse_move(-0.95, -0.95, false);
+    let mut renderer = CpuRenderer::new(SIZE as usize, SIZE as usize);
+    drag::render(&mut renderer).unwrap();
+    assert_ne!(renderer.pixel(170, 30), [0, 0, 0, 0]);
+}
+
+#[test]
+fn every_finger_places_a_rect() {
+    let mut pointers = Pointers::new();
+    let before = shakes::shakes_rect_count();
+
+    shakes::shakes_pointer(&pointers.down(touch(1, -0.5, 0.5)));
+    shakes::shakes_pointer(&pointers.down(touch(2, 0.5, -0.5)));
+    shakes::shakes_pointer(&pointers.moved(touch(2, 0.6, -0.5)));
+    assert_eq!(shakes::shakes_rect_count(), before + 2);
+
+    let mut right = pointer(3, PointerKind::Mouse, 0.0, 0.0);
+    right.button = 2;
+    shakes::shakes_pointer(&pointers.down(right));
+    assert_eq!(shakes::shakes_rect_count(), before + 2);
+}
+
+#[test]
+fn only_the_first_finger_turns_the_camera() {
+    let camera = Camera::perspective(1.0, 0.1, 100.0).looking_at([0.0, 0.0, 10.0], [0.0; 3]);
+    let mut orbit = OrbitControls::new(camera);
+    let mut pointers = Pointers::new();
+
+    camera::orbit_pointer(&mut orbit, &pointers.down(touch(1, 0.0, 0.0)));
+    camera::orbit_pointer(&mut orbit, &pointers.down(touch(2, 0.5, 0.0)));
+    camera::orbit_pointer(&mut orbit, &pointers.moved(touch(2, 0.9, 0.0)));
+    for _frame in 0..100 { orbit.update(); }
+    let moved = math::length(math::sub(orbit.camera().eye, camera.eye));
+    assert!(moved < 1e-4, "{}", moved);
+
+    camera::orbit_pointer(&mut orbit, &pointers.moved(touch(1, 0.5, 0.0)));
+    camera::orbit_pointer(&mut orbit, &pointers.up(touch(1, 0.5, 0.0)).unwrap());
+    for _frame in 0..100 { orbit.update(); }
+    assert!(orbit.camera().eye[0] < -1.0, "{:?}", orbit.camera().eye);
+    assert!(!orbit.dragging());
+}
+
+#[test]
+fn a_touch_cut_off_by_a_stop_does_not_hold_the_next_one_back() {
+    let camera = Camera::perspective(1.0, 0.1, 100.0).looking_at([0.0, 0.0, 10.0], [0.0; 3]);
+    let mut orbit = OrbitControls::new(camera);
+    let mut pointers = Pointers::new();
+
+    // the demo stops with a finger down, and its up is never seen
+    camera::orbit_pointer(&mut orbit, &pointers.down(touch(1, 0.0, 0.0)));
+    pointers.clear();
+    assert_eq!(pointers.active(), 0);
+
+    // a mouse hovering afterwards doesn't carry on the rotation
+    camera::orbit_pointer(&mut orbit, &pointers.moved(pointer(3, PointerKind::Mouse, 0.5, 0.0)));
+    for _frame in 0..100 { orbit.update(); }
+    assert!(math::length(math::sub(orbit.camera().eye, camera.eye)) < 1e-4);
+    assert!(!orbit.dragging());
+
+    // and the next finger is primary, so it turns the camera
+    let down = pointers.down(touch(2, 0.0, 0.0));
+    assert!(down.primary);
+    camera::orbit_pointer(&mut orbit, &down);
+    camera::orbit_pointer(&mut orbit, &pointers.moved(touch(2, 0.5, 0.0)));
+    for _frame in 0..100 { orbit.update(); }
+    assert!(orbit.camera().eye[0] < -1.0, "{:?}", orbit.camera().eye);
+}
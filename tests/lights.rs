@@ -0,0 +1,140 @@
+use webassembly_webgl_viewer::lights::{self, spot_factor};
+use webassembly_webgl_viewer::math;
+use webassembly_webgl_viewer::share::{self, Demo};
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-3
+}
+
+#[test]
+fn spotlights_fade_from_the_penumbra_to_the_cone() {
+    let (angle, penumbra) = (0.4, 0.5);
+    assert_eq!(spot_factor(1.0, angle, penumbra), 1.0);
+    assert_eq!(spot_factor((angle * 0.4).cos(), angle, penumbra), 1.0);
+    assert_eq!(spot_factor((angle * 1.1).cos(), angle, penumbra), 0.0);
+
+    let fading: Vec<f32> = (0..=10)
+        .map(|k| spot_factor((angle * (0.5 + 0.05 * k as f32)).cos(), angle, penumbra))
+        .collect();
+    assert!(fading.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", fading);
+}
+
+#[test]
+fn beams_with_no_fade_or_no_width_still_have_an_edge() {
+    // no penumbra is a hard edge at the cone
+    let angle: f32 = 0.4;
+    assert_eq!(spot_factor((angle * 0.99).cos(), angle, 0.0), 1.0);
+    assert_eq!(spot_factor((angle * 1.01).cos(), angle, 0.0), 0.0);
+    let edge = spot_factor(angle.cos(), angle, 0.0);
+    assert!(edge.is_finite() && (0.0..=1.0).contains(&edge));
+
+    // no width lights only straight down the beam
+    assert_eq!(spot_factor(1.0, 0.0, 0.5), 1.0);
+    assert_eq!(spot_factor(0.9, 0.0, 0.5), 0.0);
+
+    let (cone, inner) = lights::spot_cosines(0.0, 0.0);
+    assert!(cone < inner);
+}
+
+#[test]
+fn the_camera_looks_down_its_own_axis() {
+    let eye = [75.0, 30.0, 0.0];
+    let view = math::look_at(eye, [0.0; 3], [0.0, 1.0, 0.0]);
+    let at_eye = math::transform_point(&view, eye);
+    assert!(at_eye.iter().all(|v| close(*v, 0.0)), "{:?}", at_eye);
+
+    let at_target = math::transform_point(&view, [0.0; 3]);
+    assert!(close(at_target[2], -math::length(eye)), "{:?}", at_target);
+
+    let projection = math::perspective(1.0, 1.5, 0.1, 1000.0);
+    assert!(close(math::transform_point(&projection, [0.0, 0.0, -0.1])[2], -1.0));
+    assert!(close(math::transform_point(&projection, [0.0, 0.0, -1000.0])[2], 1.0));
+}
+
+#[test]
+fn normals_are_scaled_inversely() {
+    let normal = math::normal_matrix(&math::scaling([2.0, 4.0, 0.5]));
+    assert_eq!(normal, [0.5, 0.0, 0.0, 0.0, 0.25, 0.0, 0.0, 0.0, 2.0]);
+}
+
+#[test]
+fn controls_read_back_what_they_set() {
+    lights::lights_set_turn(1, 120.0);
+    assert!(close(lights::lights_turn(1), 120.0));
+
+    lights::lights_set_colour(1, "#ff8000");
+    assert_eq!(lights::lights_colour(1), "ff8000");
+    lights::lights_set_colour(1, "orange");
+    assert_eq!(lights::lights_colour(1), "ff8000");
+
+    lights::lights_set_focus(1, 2.0);
+    assert_eq!(lights::lights_focus(1), 1.0);
+
+    // lights that don't exist are left alone
+    lights::lights_set_intensity(lights::LIGHT_COUNT, 3.0);
+    assert_eq!(lights::lights_intensity(lights::LIGHT_COUNT), 0.0);
+}
+
+#[test]
+fn lights_are_shared_through_the_url() {
+    lights::lights_set_turn(2, 45.0);
+    lights::lights_set_target_height(2, 12.0);
+    lights::lights_set_intensity(2, 2.5);
+    lights::lights_set_size(2, 0.3);
+    lights::lights_set_colour(2, "#00ff00");
+    let hash = share::encode(Demo::Lights);
+
+    lights::lights_set_turn(2, 90.0);
+    lights::lights_set_target_height(2, 0.0);
+    lights::lights_set_intensity(2, 1.0);
+    lights::lights_set_colour(2, "#ffffff");
+
+    assert_eq!(share::decode(&hash), Some(Demo::Lights));
+    assert!(close(lights::lights_turn(2), 45.0));
+    assert_eq!(lights::lights_target_height(2), 12.0);
+    assert_eq!(lights::lights_intensity(2), 2.5);
+    assert_eq!(lights::lights_size(2), 0.3);
+    assert_eq!(lights::lights_colour(2), "00ff00");
+}
+
+#[test]
+fn the_cube_can_be_dragged_across_the_ground() {
+    use webassembly_webgl_viewer::camera::Camera;
+    use webassembly_webgl_viewer::input::{Pointer, PointerKind, Pointers};
+
+    let size = [800.0, 600.0];
+    let camera = Camera::perspective(75.0_f32.to_radians(), 0.1, 1000.0).looking_at([75.0, 30.0, 0.0], [0.0; 3]);
+    let on_screen = |p: [f32; 3]| {
+        let clip = math::transform_point(&camera.view_projection(size[0] / size[1]), p);
+        [(clip[0] + 1.0) / 2.0 * size[0], (1.0 - clip[1]) / 2.0 * size[1]]
+    };
+    let pointer = |offset: [f32; 2]| Pointer::at(1, PointerKind::Mouse, offset, size);
+    let mut pointers = Pointers::new();
+
+    // pressing beside the cube leaves it where it is
+    let start = lights::lights_cube_position();
+    let beside = on_screen([0.0, 0.0, 40.0]);
+    lights::lights_pointer(&pointers.down(pointer(beside)));
+    lights::lights_pointer(&pointers.moved(pointer([beside[0] + 100.0, beside[1]])));
+    lights::lights_pointer(&pointers.up(pointer([beside[0] + 100.0, beside[1]])).unwrap());
+    assert_eq!(lights::lights_cube_position(), start);
+
+    // dragging it down and to the right brings it towards the camera, which looks
+    // along -x, and over to its right, along -z
+    let grab = on_screen([start[0], start[1], start[2]]);
+    let drop = [grab[0] + 100.0, grab[1] + 50.0];
+    lights::lights_pointer(&pointers.down(pointer(grab)));
+    lights::lights_pointer(&pointers.moved(pointer(drop)));
+    lights::lights_pointer(&pointers.up(pointer(drop)).unwrap());
+
+    let moved = lights::lights_cube_position();
+    assert_eq!(moved[1], start[1]);
+    assert!(moved[0] > start[0] && moved[2] < start[2], "{:?}", moved);
+
+    // and it can't be dragged off the edge of the ground
+    let grab = on_screen([moved[0], moved[1], moved[2]]);
+    lights::lights_pointer(&pointers.down(pointer(grab)));
+    lights::lights_pointer(&pointers.moved(pointer([size[0] - 1.0, grab[1]])));
+    let edge = lights::lights_cube_position();
+    assert!(edge[2] < moved[2] && edge[2] >= -50.0, "{:?}", edge);
+}
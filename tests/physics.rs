@@ -0,0 +1,70 @@
+use euclid::Box2D;
+use webassembly_webgl_viewer::physics::{Body, World, FIXED_STEP};
+use webassembly_webgl_viewer::shakes;
+
+fn rect(x: f64, y: f64, size: f64) -> Box2D<f64, f64> {
+    Box2D::new(euclid::point2(x, y), euclid::point2(x + size, y + size))
+}
+
+fn run(world: &World, bodies: &mut [Body], seconds: f64) {
+    for _ in 0..(seconds / FIXED_STEP) as usize {
+        world.step(bodies, FIXED_STEP);
+    }
+}
+
+#[test]
+fn boxes_fall_and_come_to_rest_on_the_floor() {
+    let world = World::default();
+    let mut bodies = [Body::at_rest(rect(-0.1, 0.5, 0.2))];
+    run(&world, &mut bodies, 5.0);
+
+    assert!((bodies[0].rect.min.y - world.bounds.min.y).abs() < 1e-3);
+    assert!(bodies[0].velocity.y.abs() < 0.1);
+    assert!((bodies[0].rect.min.x + 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn boxes_stack_rather_than_pass_through_each_other() {
+    let world = World::default();
+    let mut bodies = [Body::at_rest(rect(-0.1, -1.0, 0.2)), Body::at_rest(rect(-0.05, 0.5, 0.2))];
+    run(&world, &mut bodies, 5.0);
+
+    // the top box sits on the bottom one
+    let (bottom, top) = (bodies[0].rect, bodies[1].rect);
+    assert!(top.min.y >= bottom.max.y - 0.01);
+    assert!(top.min.y < bottom.max.y + 0.05);
+}
+
+#[test]
+fn moving_boxes_bounce_off_each_other() {
+    let world = World { gravity: 0.0, ..World::default() };
+    let mut bodies = [Body::at_rest(rect(-0.5, 0.0, 0.2)), Body::at_rest(rect(0.3, 0.0, 0.2))];
+    bodies[0].velocity = euclid::vec2(1.0, 0.0);
+    run(&world, &mut bodies, 1.0);
+
+    // equal boxes swap most of their speed, losing some to the bounce
+    assert!(bodies[0].velocity.x < 0.5);
+    assert!(bodies[1].velocity.x > 0.5);
+    assert!(bodies[0].rect.max.x <= bodies[1].rect.min.x + 1e-9);
+}
+
+#[test]
+fn physics_mode_keeps_shaken_rects_on_the_canvas() {
+    let before = shakes::shakes_rect(0).unwrap();
+
+    // nothing moves until physics is turned on
+    shakes::shakes_step(1.0);
+    assert_eq!(shakes::shakes_rect(0), Some(before));
+
+    shakes::shakes_set_physics(true);
+    for _ in 0..300 {
+        shakes::shakes_step(1.0 / 60.0);
+    }
+    assert_ne!(shakes::shakes_rect(0), Some(before));
+
+    for i in 0..shakes::shakes_rect_count() {
+        let rect = shakes::shakes_rect(i).unwrap();
+        assert!(rect.min.x >= -1.0 - 1e-9 && rect.max.x <= 1.0 + 1e-9);
+        assert!(rect.min.y >= -1.0 - 1e-9 && rect.max.y <= 1.0 + 1e-9);
+    }
+}
@@ -0,0 +1,44 @@
+use webassembly_webgl_viewer::utils::{buffers_created, programs_linked, GlCache};
+
+const VERTEX: &str = "attribute vec2 coordinates; void main(void) {}";
+const FRAGMENT: &str = "precision mediump float; void main(void) {}";
+
+#[test]
+fn a_thousand_frames_link_one_program() {
+    let mut cache: GlCache<u32, u32> = GlCache::default();
+    let mut links = 0;
+
+    for _frame in 0..1000 {
+        let program = cache.program(VERTEX, FRAGMENT, || {
+            links += 1;
+            Ok::<_, ()>(7)
+        });
+        assert_eq!(program, Ok(7));
+        cache.buffer("frame", || Ok::<_, ()>(3)).unwrap();
+    }
+
+    assert_eq!(links, 1);
+    assert_eq!(programs_linked(), 1);
+    assert_eq!(buffers_created(), 1);
+}
+
+#[test]
+fn different_sources_link_different_programs() {
+    let mut cache: GlCache<u32, u32> = GlCache::default();
+
+    cache.program(VERTEX, FRAGMENT, || Ok::<_, ()>(1)).unwrap();
+    cache.program(VERTEX, "void main(void) {}", || Ok::<_, ()>(2)).unwrap();
+    let first = cache.program(VERTEX, FRAGMENT, || Ok::<_, ()>(3)).unwrap();
+
+    assert_eq!(first, 1);
+    assert_eq!(programs_linked(), 2);
+}
+
+#[test]
+fn failed_links_are_not_cached() {
+    let mut cache: GlCache<u32, u32> = GlCache::default();
+
+    assert_eq!(cache.program(VERTEX, FRAGMENT, || Err("bad")), Err("bad"));
+    assert_eq!(cache.program(VERTEX, FRAGMENT, || Ok::<_, &str>(4)), Ok(4));
+    assert_eq!(programs_linked(), 1);
+}
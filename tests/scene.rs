@@ -0,0 +1,96 @@
+use webassembly_webgl_viewer::scene::{self, Scene, SCENE_VERSION};
+use webassembly_webgl_viewer::{drag, history, shakes};
+
+// the kind of error an import is rejected with
+fn rejection(json: &str) -> String {
+    match scene::Scene::from_json(json) {
+        Ok(_) => panic!("scene was accepted: {}", json),
+        Err(e) => {
+            assert_eq!(e.kind(), "invalid_scene");
+            e.to_string()
+        }
+    }
+}
+
+// the starting scene with one change made to it
+fn edited(edit: impl FnOnce(&mut Scene)) -> String {
+    let mut scene = Scene::current();
+    edit(&mut scene);
+    serde_json::to_string(&scene).unwrap()
+}
+
+#[test]
+fn the_starting_scene_round_trips() {
+    let json = scene::export_scene();
+    scene::import_scene(&json).unwrap();
+    assert_eq!(scene::export_scene(), json);
+}
+
+#[test]
+fn edits_survive_a_round_trip() {
+    drag::drag_set_polygon(true);
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    drag::drag_mouse_down(-0.45, 0.05, false);
+    shakes::shakes_mouse_down(0.2, 0.2, false);
+
+    let json = scene::export_scene();
+    let saved = Scene::from_json(&json).unwrap();
+    assert_eq!(saved.version, SCENE_VERSION);
+    assert!(saved.drag.polygon);
+    assert_eq!(saved.drag.spaces.len(), 2);
+    assert_eq!(saved.drag.spaces[0].vertices.len(), 5);
+    assert_eq!(saved.shakes.rects.len(), 3);
+
+    // put everything back the way it started, then load the saved layout
+    while history::undo() {}
+    drag::drag_set_polygon(false);
+    assert_ne!(scene::export_scene(), json);
+
+    scene::import_scene(&json).unwrap();
+    assert_eq!(scene::export_scene(), json);
+    assert_eq!(drag::drag_space_count(), 2);
+    assert_eq!(shakes::shakes_rect_count(), 3);
+}
+
+#[test]
+fn importing_clears_the_history() {
+    shakes::shakes_mouse_down(0.2, 0.2, false);
+    assert!(history::can_undo());
+
+    scene::import_scene(&scene::export_scene()).unwrap();
+    assert!(!history::can_undo());
+}
+
+#[test]
+fn malformed_json_is_rejected() {
+    rejection("");
+    rejection("{\"version\": 1");
+    rejection("[1, 2, 3]");
+    rejection(&edited(|_| {}).replace("\"moving\"", "\"mooving\""));
+}
+
+#[test]
+fn versions_are_checked() {
+    assert!(rejection("{}").contains("missing version"));
+    assert!(rejection(&edited(|s| s.version = SCENE_VERSION + 1)).contains("not supported"));
+}
+
+#[test]
+fn bad_values_are_rejected() {
+    rejection(&edited(|s| s.drag.spaces[0].vertices.truncate(2)));
+    rejection(&edited(|s| s.drag.spaces[0].vertices[0].size = [0.0, 0.1]));
+    rejection(&edited(|s| s.drag.spaces[0].vertices[0].colour = [2.0, 0.0, 0.0]));
+    rejection(&edited(|s| s.shakes.shake = 0.0));
+    rejection(&edited(|_| {}).replace("\"shake\":1.0", "\"shake\":1e400"));
+}
+
+#[test]
+fn a_rejected_scene_changes_nothing() {
+    let before = scene::export_scene();
+    let bad = edited(|s| {
+        s.drag.spaces.clear();
+        s.shakes.shake = -1.0;
+    });
+    assert!(scene::import_scene(&bad).is_err());
+    assert_eq!(scene::export_scene(), before);
+}
@@ -0,0 +1,71 @@
+use webassembly_webgl_viewer::lights;
+use webassembly_webgl_viewer::math;
+use webassembly_webgl_viewer::share::{self, Demo};
+use webassembly_webgl_viewer::shadow::{self, pcf_visibility};
+
+#[test]
+fn depths_survive_packing() {
+    for k in 0..=100 {
+        let depth = k as f32 / 100.0 * 0.999;
+        let packed = shadow::pack_depth(depth);
+        assert!(packed.iter().all(|c| (0.0..1.0).contains(c)), "{:?}", packed);
+
+        // the texture only keeps 8 bits a channel
+        let stored = packed.map(|c| (c * 255.0).round() / 255.0);
+        assert!((shadow::unpack_depth(stored) - depth).abs() < 1e-6, "{}", depth);
+    }
+}
+
+#[test]
+fn the_light_sees_down_its_beam() {
+    let position = [-50.0, 50.0, 0.0];
+    let direction = math::normalize([50.0, -50.0, 0.0]);
+    let light = shadow::light_view_projection(position, direction, 0.1);
+
+    let centre = math::transform_point(&light, math::add(position, math::scale(direction, 70.0)));
+    assert!(centre[0].abs() < 1e-4 && centre[1].abs() < 1e-4, "{:?}", centre);
+
+    // nearer things have smaller depths
+    let near = math::transform_point(&light, math::add(position, math::scale(direction, 20.0)));
+    assert!(near[2] < centre[2]);
+}
+
+#[test]
+fn pcf_softens_the_edge_of_a_shadow() {
+    let texel = 0.1;
+
+    // something at depth 0.5 covers the left half of the map
+    let closest = |u: f32, _v: f32| if u < 0.5 { 0.5 } else { 1.0 };
+
+    assert_eq!(pcf_visibility(closest, [0.2, 0.5], 0.8, 0.001, texel), 0.0);
+    assert_eq!(pcf_visibility(closest, [0.8, 0.5], 0.8, 0.001, texel), 1.0);
+    let edge = pcf_visibility(closest, [0.55, 0.5], 0.8, 0.001, texel);
+    assert!((edge - 2.0 / 3.0).abs() < 1e-6, "{}", edge);
+
+    // the bias keeps surfaces from shadowing themselves
+    assert_eq!(pcf_visibility(closest, [0.2, 0.5], 0.5005, 0.001, texel), 1.0);
+}
+
+#[test]
+fn shadow_settings_are_clamped_and_shared() {
+    lights::lights_set_shadow_resolution(16);
+    assert_eq!(lights::lights_shadow_resolution(), shadow::MIN_SHADOW_RESOLUTION);
+    lights::lights_set_shadow_resolution(100_000);
+    assert_eq!(lights::lights_shadow_resolution(), shadow::MAX_SHADOW_RESOLUTION);
+
+    lights::lights_set_shadow_bias(-1.0);
+    assert_eq!(lights::lights_shadow_bias(), 0.0);
+
+    lights::lights_set_shadow_resolution(512);
+    lights::lights_set_shadow_bias(0.0003);
+    let hash = share::encode(Demo::Lights);
+    lights::lights_set_shadow_resolution(2048);
+    lights::lights_set_shadow_bias(0.001);
+
+    share::decode(&hash);
+    assert_eq!(lights::lights_shadow_resolution(), 512);
+    assert_eq!(lights::lights_shadow_bias(), 0.0003);
+
+    // the bias is kept short, in the slider's steps
+    assert!(hash.contains("&shadow_bias=3"), "{}", hash);
+}
@@ -0,0 +1,127 @@
+use webassembly_webgl_viewer::clock;
+use webassembly_webgl_viewer::share::{self, Demo};
+use webassembly_webgl_viewer::{drag, gradient, point, shakes, sin_wave, special};
+
+#[test]
+fn numbers_are_kept_short() {
+    assert_eq!(share::number(1.0), "1");
+    assert_eq!(share::number(0.5), "0.5");
+    assert_eq!(share::number(0.12345), "0.123");
+    assert_eq!(share::number(-0.0001), "0");
+    assert_eq!(share::groups(&[vec![1.0, 2.5], vec![-0.25]]), "1,2.5;-0.25");
+}
+
+#[test]
+fn slider_demos_encode_their_settings() {
+    special::update_sides(5);
+    special::update_rotation_speed(20);
+    special::update_colour_speed(70);
+    assert_eq!(share::encode(Demo::Special), "special/sides=5&rotate=20&colour=70");
+
+    gradient::g_update_sides(12);
+    assert_eq!(share::encode(Demo::Gradient), "gradient/sides=12");
+
+    point::p_update_resolution(7);
+    point::p_update_box(true);
+    assert_eq!(share::encode(Demo::Point), "point/res=7&pointwise=1");
+
+    sin_wave::s_update_wavelength(12.5);
+    sin_wave::s_mouse_move(0.25, -0.5);
+    sin_wave::s_update_resolution(30);
+    assert_eq!(share::encode(Demo::SinWave), "sin_wave/wavelength=12.5&center=0.25,-0.5&res=30");
+}
+
+#[test]
+fn hashes_load_into_the_demo_they_name() {
+    assert_eq!(share::decode("#special/sides=3&rotate=10&colour=90"), Some(Demo::Special));
+    assert_eq!(share::encode(Demo::Special), "special/sides=3&rotate=10&colour=90");
+
+    assert_eq!(share::decode("#gradient/sides=6"), Some(Demo::Gradient));
+    assert_eq!(share::encode(Demo::Gradient), "gradient/sides=6");
+
+    assert_eq!(share::decode("point/res=3&pointwise=1"), Some(Demo::Point));
+    assert_eq!(share::encode(Demo::Point), "point/res=3&pointwise=1");
+
+    assert_eq!(share::decode("#sin_wave/wavelength=4&center=-0.1,0.2&res=9"), Some(Demo::SinWave));
+    assert_eq!(share::encode(Demo::SinWave), "sin_wave/wavelength=4&center=-0.1,0.2&res=9");
+}
+
+#[test]
+fn layouts_round_trip() {
+    drag::drag_set_polygon(true);
+    drag::drag_add_space(0.6, -0.9, 0.3, 0.3);
+    drag::drag_mouse_down(-0.45, 0.05, false);
+    let drag_hash = share::encode(Demo::Drag);
+
+    shakes::shakes_mouse_down(0.2, 0.2, false);
+    let shakes_hash = share::encode(Demo::Shakes);
+
+    // load something else, then the saved hashes
+    share::decode("#drag/polygon=0&spaces=0,0,0.5,0,0,0.5");
+    share::decode("#shakes/rects=");
+    assert!(!drag::drag_polygon());
+    assert_eq!(drag::drag_space_count(), 1);
+    assert_eq!(shakes::shakes_rect_count(), 0);
+
+    share::decode(&drag_hash);
+    share::decode(&shakes_hash);
+    assert_eq!(share::encode(Demo::Drag), drag_hash);
+    assert_eq!(share::encode(Demo::Shakes), shakes_hash);
+    assert!(drag::drag_polygon());
+    assert_eq!(drag::drag_space_count(), 2);
+    assert_eq!(shakes::shakes_rect_count(), 3);
+}
+
+#[test]
+fn bad_values_are_ignored() {
+    point::p_update_resolution(4);
+    point::p_update_box(false);
+    share::decode("#point/res=abc&pointwise=2");
+    assert_eq!(share::encode(Demo::Point), "point/res=4&pointwise=0");
+
+    share::decode("#point/res=-3");
+    assert_eq!(share::encode(Demo::Point), "point/res=4&pointwise=0");
+
+    // a space with too few points, or half a point, throws out the whole list
+    let before = share::encode(Demo::Drag);
+    share::decode("#drag/spaces=0,0,1,1");
+    share::decode("#drag/spaces=0,0,1,1,1,0,0");
+    share::decode("#drag/spaces=0,0,1,x,1,0");
+    assert_eq!(share::encode(Demo::Drag), before);
+
+    assert_eq!(share::decode("#3js/l0_i=2"), None);
+    assert_eq!(share::decode(""), None);
+}
+
+#[test]
+fn sizes_outside_the_sliders_are_ignored() {
+    point::p_update_resolution(4);
+    point::p_update_box(false);
+    share::decode("#point/res=100000");
+    assert_eq!(share::encode(Demo::Point), "point/res=4&pointwise=0");
+
+    sin_wave::s_update_resolution(30);
+    share::decode("#sin_wave/res=2000000000");
+    assert!(share::encode(Demo::SinWave).ends_with("&res=30"));
+    share::decode("#sin_wave/res=100");
+    assert!(share::encode(Demo::SinWave).ends_with("&res=100"));
+
+    special::update_sides(5);
+    share::decode("#special/sides=2000000000");
+    assert!(share::encode(Demo::Special).starts_with("special/sides=5&"));
+
+    gradient::g_update_sides(12);
+    share::decode("#gradient/sides=2");
+    assert_eq!(share::encode(Demo::Gradient), "gradient/sides=12");
+}
+
+#[test]
+fn the_sin_wave_center_is_shared_once_it_rests() {
+    sin_wave::s_mouse_move(0.1, 0.2);
+    sin_wave::s_mouse_move(0.3, 0.4);
+
+    // not while it's still moving, then once for the whole move
+    assert!(!sin_wave::share_center(clock::now()));
+    assert!(sin_wave::share_center(clock::now() + 1000.0));
+    assert!(!sin_wave::share_center(f64::INFINITY));
+}
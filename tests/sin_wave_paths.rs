@@ -0,0 +1,44 @@
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+use webassembly_webgl_viewer::sin_wave;
+
+const SIZE: usize = 256;
+const RESOLUTION: usize = 8;
+
+#[test]
+fn gpu_and_cpu_paths_agree_on_the_grid() {
+    sin_wave::s_update_resolution(RESOLUTION as i32);
+    sin_wave::s_update_wavelength(4.0);
+    sin_wave::s_mouse_move(0.2, -0.1);
+    sin_wave::s_set_quantised(true);
+
+    let mut vertices = CpuRenderer::new(SIZE, SIZE);
+    sin_wave::render(&mut vertices).unwrap();
+
+    let mut fragments = CpuRenderer::new(SIZE, SIZE);
+    sin_wave::render_fragments(&mut fragments);
+
+    // the cpu path is exact at each corner of the grid, random tint included
+    let spacing = SIZE / RESOLUTION;
+    for y in (0..SIZE).step_by(spacing) {
+        for x in (0..SIZE).step_by(spacing) {
+            let cpu = vertices.pixel(x, y);
+            let gpu = fragments.pixel(x, y);
+            let close = cpu.iter().zip(gpu.iter()).all(|(c, g)| c.abs_diff(*g) <= 4);
+            assert!(close, "({}, {}): cpu {:?} gpu {:?}", x, y, cpu, gpu);
+        }
+    }
+}
+
+#[test]
+fn quantised_fragments_are_flat_around_each_corner() {
+    sin_wave::s_update_resolution(RESOLUTION as i32);
+    sin_wave::s_set_quantised(true);
+
+    let mut fragments = CpuRenderer::new(SIZE, SIZE);
+    sin_wave::render_fragments(&mut fragments);
+
+    let spacing = SIZE / RESOLUTION;
+    let corner = fragments.pixel(spacing, spacing);
+    assert_eq!(fragments.pixel(spacing + 3, spacing - 3), corner);
+    assert_eq!(fragments.pixel(spacing - 3, spacing + 3), corner);
+}
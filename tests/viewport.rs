@@ -0,0 +1,59 @@
+use webassembly_webgl_viewer::renderer::CpuRenderer;
+use webassembly_webgl_viewer::viewport::{self, Viewport};
+use webassembly_webgl_viewer::{gradient, special};
+
+#[test]
+fn the_shorter_side_spans_the_world() {
+    let wide = Viewport::new(800, 400);
+    assert_eq!(wide.world_to_clip([1.0, 1.0]), [0.5, 1.0]);
+    assert_eq!(wide.clip_to_world([1.0, -1.0]), [2.0, -1.0]);
+
+    let tall = Viewport::new(300, 600);
+    assert_eq!(tall.world_to_clip([1.0, 1.0]), [1.0, 0.5]);
+
+    assert_eq!(Viewport::new(500, 500).world_to_clip([0.3, -0.7]), [0.3, -0.7]);
+    assert_eq!(Viewport::new(0, 0), Viewport::new(1, 1));
+}
+
+#[test]
+fn drawing_buffers_follow_the_pixel_ratio() {
+    assert_eq!(viewport::drawing_buffer_size(300.0, 150.0, 1.0), (300, 150));
+    assert_eq!(viewport::drawing_buffer_size(300.0, 150.0, 2.0), (600, 300));
+    assert_eq!(viewport::drawing_buffer_size(333.3, 100.0, 1.5), (500, 150));
+    assert_eq!(viewport::drawing_buffer_size(0.0, 0.0, 2.0), (1, 1));
+}
+
+#[test]
+fn the_mouse_maps_from_where_the_canvas_is_now() {
+    // a canvas 200 by 100 that has scrolled to 50, 20
+    let (origin, size) = ([50.0, 20.0], [200.0, 100.0]);
+    assert_eq!(viewport::client_to_clip([50.0, 20.0], origin, size), [-1.0, 1.0]);
+    assert_eq!(viewport::client_to_clip([150.0, 70.0], origin, size), [0.0, 0.0]);
+    assert_eq!(viewport::client_to_clip([250.0, 120.0], origin, size), [1.0, -1.0]);
+}
+
+// width and height in pixels of everything drawn
+fn drawn_extent(renderer: &CpuRenderer) -> (usize, usize) {
+    let drawn: Vec<(usize, usize)> = (0..renderer.height())
+        .flat_map(|y| (0..renderer.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| renderer.pixel(x, y)[3] > 0)
+        .collect();
+    let width = drawn.iter().map(|p| p.0).max().unwrap() - drawn.iter().map(|p| p.0).min().unwrap() + 1;
+    let height = drawn.iter().map(|p| p.1).max().unwrap() - drawn.iter().map(|p| p.1).min().unwrap() + 1;
+    (width, height)
+}
+
+#[test]
+fn ngons_stay_round_on_wide_canvases() {
+    special::update_sides(64);
+    let mut renderer = CpuRenderer::new(240, 120);
+    special::render(&mut renderer).unwrap();
+    let (width, height) = drawn_extent(&renderer);
+    assert!(width.abs_diff(height) <= 2, "{} by {}", width, height);
+
+    gradient::g_update_sides(64);
+    let mut renderer = CpuRenderer::new(120, 240);
+    gradient::render(&mut renderer).unwrap();
+    let (width, height) = drawn_extent(&renderer);
+    assert!(width.abs_diff(height) <= 2, "{} by {}", width, height);
+}